@@ -0,0 +1,415 @@
+//! Generador de carga HTTP para medir el servidor.
+//!
+//! Uso:
+//!   bench [--target=127.0.0.1:8080] [--connections=8]
+//!         [--duration=10s | --requests=1000]
+//!         [--mix=/fibonacci?num=20:5,/hash?text=abc:3,/sleep?seconds=0:1]
+//!         [--idle=0] [--timeout=60s] [--json=resultado.json]
+//!
+//! Cada conexión es un hilo que abre un socket por petición (el servidor
+//! responde en HTTP/1.0 y cierra), elige una ruta según los pesos de `--mix`
+//! y mide la latencia hasta leer la respuesta completa.
+//!
+//! `--idle=N` abre además N conexiones que no mandan nada en toda la corrida,
+//! como clientes lentos: sirve para comparar `--frontend=events` con
+//! `--frontend=blocking` en el servidor.
+
+use proyecto_1::registry::parse_duration;
+use proyecto_1::stats::{histogram, HistogramBucket, LatencySummary};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Cuándo termina la corrida
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stop {
+    Duration(Duration),
+    Requests(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Options {
+    target: String,
+    connections: usize,
+    stop: Stop,
+    /// Rutas (con query) y su peso relativo
+    mix: Vec<(String, u32)>,
+    /// Conexiones abiertas que no completan su petición
+    idle: usize,
+    /// Espera máxima por la respuesta de cada petición
+    timeout: Duration,
+    json: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            target: "127.0.0.1:8080".to_string(),
+            connections: 8,
+            stop: Stop::Duration(Duration::from_secs(10)),
+            mix: vec![
+                ("/fibonacci?num=20".to_string(), 5),
+                ("/hash?text=abc".to_string(), 3),
+                ("/sleep?seconds=0".to_string(), 1),
+            ],
+            idle: 0,
+            timeout: Duration::from_secs(60),
+            json: None,
+        }
+    }
+}
+
+/// Parsea `ruta:peso,ruta:peso`; el peso es opcional (por defecto 1)
+fn parse_mix(v: &str) -> Result<Vec<(String, u32)>, String> {
+    let mut mix = Vec::new();
+    for item in v.split(',').filter(|s| !s.is_empty()) {
+        let (path, weight) = match item.rsplit_once(':') {
+            Some((p, w)) => (p, w.parse::<u32>().map_err(|_| format!("Peso inválido en '{}'", item))?),
+            None => (item, 1),
+        };
+        if !path.starts_with('/') {
+            return Err(format!("La ruta debe empezar con '/': '{}'", path));
+        }
+        if weight > 0 {
+            mix.push((path.to_string(), weight));
+        }
+    }
+    if mix.is_empty() {
+        return Err("La mezcla de rutas está vacía".to_string());
+    }
+    Ok(mix)
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut opts = Options::default();
+    for arg in args {
+        let (name, value) = arg
+            .strip_prefix("--")
+            .and_then(|a| a.split_once('='))
+            .ok_or_else(|| format!("Argumento inválido: '{}' (se espera --opcion=valor)", arg))?;
+        match name {
+            "target" => opts.target = value.to_string(),
+            "connections" => {
+                opts.connections = value
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or("'--connections' debe ser un entero positivo")?
+            }
+            "duration" => opts.stop = Stop::Duration(parse_duration(value)?),
+            "requests" => {
+                opts.stop = Stop::Requests(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or("'--requests' debe ser un entero positivo")?,
+                )
+            }
+            "mix" => opts.mix = parse_mix(value)?,
+            "idle" => opts.idle = value.parse().map_err(|_| "'--idle' debe ser un entero no negativo")?,
+            "timeout" => {
+                opts.timeout = Some(parse_duration(value)?)
+                    .filter(|d| !d.is_zero())
+                    .ok_or("'--timeout' debe ser una duración positiva")?
+            }
+            "json" => opts.json = Some(value.to_string()),
+            _ => return Err(format!("Opción desconocida: '--{}'", name)),
+        }
+    }
+    Ok(opts)
+}
+
+/// Elige una ruta de la mezcla según su peso
+fn pick(mix: &[(String, u32)], rng: &mut impl Rng) -> usize {
+    let total: u32 = mix.iter().map(|(_, w)| w).sum();
+    let mut r = rng.gen_range(0..total);
+    for (i, (_, w)) in mix.iter().enumerate() {
+        if r < *w {
+            return i;
+        }
+        r -= w;
+    }
+    mix.len() - 1
+}
+
+/// Resultado de una petición
+enum Outcome {
+    /// Código de estado HTTP recibido
+    Status(u16),
+    /// Fallo de conexión o de lectura/escritura
+    IoError,
+}
+
+fn send(target: &str, path: &str, timeout: Duration) -> Outcome {
+    let Ok(mut stream) = TcpStream::connect(target) else {
+        return Outcome::IoError;
+    };
+    let _ = stream.set_read_timeout(Some(timeout));
+    let req = format!("GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, target);
+    if stream.write_all(req.as_bytes()).is_err() {
+        return Outcome::IoError;
+    }
+    let mut resp = Vec::new();
+    if stream.read_to_end(&mut resp).is_err() && resp.is_empty() {
+        return Outcome::IoError;
+    }
+    // "HTTP/1.0 200 OK" -> 200
+    String::from_utf8_lossy(&resp)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|c| c.parse().ok())
+        .map(Outcome::Status)
+        .unwrap_or(Outcome::IoError)
+}
+
+/// Abre `n` conexiones que no mandan nada
+fn open_idle(target: &str, n: usize) -> Vec<TcpStream> {
+    let mut conns = Vec::with_capacity(n);
+    for _ in 0..n {
+        match TcpStream::connect(target) {
+            Ok(s) => conns.push(s),
+            Err(e) => {
+                eprintln!("Solo se abrieron {} de {} conexiones ociosas: {}", conns.len(), n, e);
+                break;
+            }
+        }
+    }
+    conns
+}
+
+/// Muestras acumuladas de una ruta
+#[derive(Default)]
+struct RouteSamples {
+    latencies_ms: Vec<f64>,
+    statuses: BTreeMap<u16, usize>,
+    io_errors: usize,
+}
+
+#[derive(Serialize)]
+struct RouteReport {
+    path: String,
+    weight: u32,
+    requests: usize,
+    errors: usize,
+    io_errors: usize,
+    statuses: BTreeMap<u16, usize>,
+    latency_ms: LatencySummary,
+    histogram: Vec<HistogramBucket>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    target: String,
+    connections: usize,
+    /// Conexiones calladas que se lograron abrir
+    idle_connections: usize,
+    elapsed_secs: f64,
+    requests: usize,
+    errors: usize,
+    throughput_per_sec: f64,
+    latency_ms: LatencySummary,
+    histogram: Vec<HistogramBucket>,
+    routes: Vec<RouteReport>,
+}
+
+fn run(opts: &Options) -> Report {
+    let samples: Arc<Vec<Mutex<RouteSamples>>> =
+        Arc::new(opts.mix.iter().map(|_| Mutex::new(RouteSamples::default())).collect());
+    let issued = Arc::new(AtomicUsize::new(0));
+    // Se cierran recién al terminar la corrida
+    let idle = open_idle(&opts.target, opts.idle);
+    let start = Instant::now();
+
+    let handles: Vec<_> = (0..opts.connections)
+        .map(|_| {
+            let (opts, samples, issued) = (opts.clone(), samples.clone(), issued.clone());
+            thread::spawn(move || {
+                let mut rng = rand::thread_rng();
+                loop {
+                    match opts.stop {
+                        Stop::Duration(d) if start.elapsed() >= d => break,
+                        Stop::Requests(n) if issued.fetch_add(1, Ordering::SeqCst) >= n => break,
+                        _ => {}
+                    }
+                    let i = pick(&opts.mix, &mut rng);
+                    let t = Instant::now();
+                    let outcome = send(&opts.target, &opts.mix[i].0, opts.timeout);
+                    let ms = t.elapsed().as_secs_f64() * 1000.0;
+
+                    let mut s = samples[i].lock().unwrap();
+                    match outcome {
+                        Outcome::Status(code) => {
+                            s.latencies_ms.push(ms);
+                            *s.statuses.entry(code).or_default() += 1;
+                        }
+                        Outcome::IoError => s.io_errors += 1,
+                    }
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        let _ = h.join();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let idle_connections = idle.len();
+    drop(idle);
+
+    let mut all = Vec::new();
+    let mut routes = Vec::new();
+    for ((path, weight), s) in opts.mix.iter().zip(samples.iter()) {
+        let mut s = s.lock().unwrap();
+        let bad_status: usize = s.statuses.iter().filter(|(c, _)| !(200..300).contains(*c)).map(|(_, n)| n).sum();
+        all.extend_from_slice(&s.latencies_ms);
+        routes.push(RouteReport {
+            path: path.clone(),
+            weight: *weight,
+            requests: s.latencies_ms.len() + s.io_errors,
+            errors: bad_status + s.io_errors,
+            io_errors: s.io_errors,
+            statuses: s.statuses.clone(),
+            histogram: histogram(&s.latencies_ms),
+            latency_ms: LatencySummary::from_samples(&mut s.latencies_ms),
+        });
+    }
+
+    let requests = routes.iter().map(|r| r.requests).sum();
+    Report {
+        target: opts.target.clone(),
+        connections: opts.connections,
+        idle_connections,
+        elapsed_secs: elapsed,
+        requests,
+        errors: routes.iter().map(|r| r.errors).sum(),
+        throughput_per_sec: if elapsed > 0.0 { requests as f64 / elapsed } else { 0.0 },
+        histogram: histogram(&all),
+        latency_ms: LatencySummary::from_samples(&mut all),
+        routes,
+    }
+}
+
+fn print_histogram(h: &[HistogramBucket]) {
+    let max = h.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    for b in h.iter().filter(|b| b.count > 0) {
+        let label = match b.lt_ms {
+            Some(ms) => format!("< {} ms", ms),
+            None => format!(">= {} ms", proyecto_1::stats::HISTOGRAM_BOUNDS_MS.last().unwrap()),
+        };
+        let bar = "#".repeat((b.count * 40).div_ceil(max));
+        println!("    {:>12} {:>8} {}", label, b.count, bar);
+    }
+}
+
+fn print_summary(l: &LatencySummary) {
+    println!(
+        "    latencia ms: min {:.2}  media {:.2}  p50 {:.2}  p95 {:.2}  p99 {:.2}  max {:.2}",
+        l.min, l.mean, l.p50, l.p95, l.p99, l.max
+    );
+}
+
+fn print_report(r: &Report) {
+    println!("Objetivo: {}  conexiones: {}  ociosas: {}", r.target, r.connections, r.idle_connections);
+    println!(
+        "Peticiones: {}  errores: {}  tiempo: {:.2} s  throughput: {:.1} req/s",
+        r.requests, r.errors, r.elapsed_secs, r.throughput_per_sec
+    );
+    print_summary(&r.latency_ms);
+    print_histogram(&r.histogram);
+    for route in &r.routes {
+        println!();
+        println!(
+            "{} (peso {}): {} peticiones, {} errores ({} de E/S), estados {:?}",
+            route.path, route.weight, route.requests, route.errors, route.io_errors, route.statuses
+        );
+        print_summary(&route.latency_ms);
+        print_histogram(&route.histogram);
+    }
+}
+
+fn main() {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(2);
+        }
+    };
+
+    let report = run(&opts);
+    print_report(&report);
+
+    if let Some(path) = &opts.json {
+        let json = serde_json::to_string_pretty(&report).unwrap();
+        if let Err(e) = std::fs::write(path, json) {
+            eprintln!("No se pudo escribir '{}': {}", path, e);
+            process::exit(1);
+        }
+        println!("\nResultados exportados a '{}'", path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_mix_weights() {
+        let mix = parse_mix("/fibonacci?num=20:5,/hash?text=a:b:2,/status").unwrap();
+        assert_eq!(mix[0], ("/fibonacci?num=20".to_string(), 5));
+        assert_eq!(mix[1], ("/hash?text=a:b".to_string(), 2));
+        assert_eq!(mix[2], ("/status".to_string(), 1));
+        assert!(parse_mix("fibonacci:1").is_err());
+        assert!(parse_mix("/x:0").is_err());
+    }
+
+    #[test]
+    fn parse_args_stop_conditions() {
+        let o = parse_args(args(&["--requests=100", "--connections=2"])).unwrap();
+        assert_eq!(o.stop, Stop::Requests(100));
+        assert_eq!(o.connections, 2);
+        assert!(parse_args(args(&["--connections=0"])).is_err());
+        let o = parse_args(args(&["--idle=500", "--timeout=2s"])).unwrap();
+        assert_eq!((o.idle, o.timeout), (500, Duration::from_secs(2)));
+        assert!(parse_args(args(&["--timeout=0s"])).is_err());
+        assert!(parse_args(args(&["--nope=1"])).is_err());
+    }
+
+    #[test]
+    fn run_against_local_server_counts_requests() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for mut s in listener.incoming().flatten() {
+                let mut buf = [0; 1024];
+                let _ = s.read(&mut buf);
+                let _ = s.write_all(b"HTTP/1.0 200 OK\r\n\r\nok");
+            }
+        });
+
+        let opts = Options {
+            target: addr,
+            connections: 2,
+            stop: Stop::Requests(10),
+            mix: vec![("/a".to_string(), 1), ("/b".to_string(), 1)],
+            idle: 0,
+            timeout: Duration::from_secs(5),
+            json: None,
+        };
+        let report = run(&opts);
+        assert_eq!(report.requests, 10);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.latency_ms.count, 10);
+    }
+}
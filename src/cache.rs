@@ -0,0 +1,538 @@
+use crate::http::{self, Request};
+use crate::middleware::{Middleware, Next};
+use crate::registry::{parse_duration, ParamKind, ParamSpec, Params};
+use crate::singleflight::Singleflight;
+use crate::state::SharedState;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Largo máximo de la clave que se muestra en /admin/cache
+const KEY_PREVIEW: usize = 80;
+
+/// Tamaño y vencimientos de la caché de respuestas
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Respuestas guardadas como máximo
+    pub max_entries: usize,
+    /// Bytes guardados como máximo (claves más respuestas)
+    pub max_bytes: usize,
+    /// Vencimiento por ruta; reemplaza al que la ruta trae de fábrica
+    pub ttls: BTreeMap<String, Duration>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings { enabled: true, max_entries: 1024, max_bytes: 16 * 1024 * 1024, ttls: BTreeMap::new() }
+    }
+}
+
+/// Parsea `ruta:duración,ruta:duración` (p. ej. `/hash:10m,/fibonacci:60m`)
+///   - Err si falta la duración, no es válida o la ruta no empieza con '/'
+pub fn parse_ttls(value: &str) -> Result<BTreeMap<String, Duration>, String> {
+    let mut ttls = BTreeMap::new();
+    for item in value.split(',').filter(|s| !s.is_empty()) {
+        let (route, ttl) = item
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Se espera ruta:duración en '{}'", item))?;
+        if !route.starts_with('/') {
+            return Err(format!("La ruta debe empezar con '/': '{}'", route));
+        }
+        ttls.insert(route.to_string(), parse_duration(ttl)?);
+    }
+    Ok(ttls)
+}
+
+/// Revisa que los vencimientos de `--cache-ttl` sean de rutas que se cachean
+///   - Err con la primera ruta que no tiene caché (ruta inexistente o mal escrita)
+pub fn check_ttls(settings: &CacheSettings, cached: &[&str]) -> Result<(), String> {
+    match settings.ttls.keys().find(|route| !cached.contains(&route.as_str())) {
+        Some(route) => Err(format!(
+            "Opción '--cache-ttl': la ruta '{}' no se cachea (rutas con caché: {})",
+            route,
+            cached.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Contadores de la caché de una ruta
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Peticiones con `Cache-Control: no-cache` o `no-store`
+    pub bypassed: usize,
+    pub stored: usize,
+    /// Sacadas por falta de lugar (la usada hace más tiempo primero)
+    pub evicted: usize,
+    /// Encontradas ya vencidas
+    pub expired: usize,
+}
+
+impl CacheStats {
+    pub fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.bypassed += other.bypassed;
+        self.stored += other.stored;
+        self.evicted += other.evicted;
+        self.expired += other.expired;
+    }
+
+    /// Aciertos sobre búsquedas (0 si no hubo ninguna)
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            n => self.hits as f64 / n as f64,
+        }
+    }
+}
+
+/// Respuesta guardada
+struct Entry {
+    route: String,
+    response: String,
+    stored: Instant,
+    expires: Instant,
+    /// Orden de uso: la de menor valor es la menos reciente
+    tick: u64,
+}
+
+impl Entry {
+    fn bytes(&self, key: &str) -> usize {
+        key.len() + self.response.len()
+    }
+}
+
+/// Caché LRU de respuestas acotada en entradas y en bytes
+pub struct ResponseCache {
+    settings: CacheSettings,
+    entries: HashMap<String, Entry>,
+    /// Claves por orden de uso
+    lru: BTreeMap<u64, String>,
+    tick: u64,
+    bytes: usize,
+    stats: BTreeMap<String, CacheStats>,
+}
+
+/// Resumen de la caché para /status y /admin/cache
+#[derive(Serialize)]
+pub struct CacheReport {
+    enabled: bool,
+    entries: usize,
+    bytes: usize,
+    max_entries: usize,
+    max_bytes: usize,
+    #[serde(flatten)]
+    totals: CacheStats,
+    hit_ratio: f64,
+    routes: BTreeMap<String, RouteReport>,
+}
+
+#[derive(Serialize)]
+struct RouteReport {
+    #[serde(flatten)]
+    stats: CacheStats,
+    hit_ratio: f64,
+}
+
+/// Entrada listada en /admin/cache
+#[derive(Serialize)]
+struct EntryReport {
+    route: String,
+    key: String,
+    bytes: usize,
+    age_secs: f64,
+    expires_in_secs: f64,
+}
+
+impl ResponseCache {
+    pub fn new(settings: CacheSettings) -> Self {
+        ResponseCache {
+            settings,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            bytes: 0,
+            stats: BTreeMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    /// Vencimiento para `route`: el configurado o, si no hay, `default`
+    pub fn ttl(&self, route: &str, default: Duration) -> Duration {
+        self.settings.ttls.get(route).copied().unwrap_or(default)
+    }
+
+    fn stats_mut(&mut self, route: &str) -> &mut CacheStats {
+        self.stats.entry(route.to_string()).or_default()
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Busca la respuesta de `key` y la marca como la más reciente
+    ///   - Some(respuesta, edad) si estaba y no venció
+    pub fn get(&mut self, key: &str, route: &str, now: Instant) -> Option<(String, Duration)> {
+        let expired = match self.entries.get(key) {
+            None => {
+                self.stats_mut(route).misses += 1;
+                return None;
+            }
+            Some(e) => e.expires <= now,
+        };
+        if expired {
+            self.remove(key);
+            let stats = self.stats_mut(route);
+            stats.expired += 1;
+            stats.misses += 1;
+            return None;
+        }
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key).unwrap();
+        self.lru.remove(&entry.tick);
+        entry.tick = tick;
+        self.lru.insert(tick, key.to_string());
+        let found = (entry.response.clone(), now - entry.stored);
+        self.stats_mut(route).hits += 1;
+        Some(found)
+    }
+
+    /// Cuenta una petición que pidió no usar la caché
+    pub fn bypass(&mut self, route: &str) {
+        self.stats_mut(route).bypassed += 1;
+    }
+
+    /// Guarda (o reemplaza) la respuesta de `key` y saca las menos usadas
+    /// hasta volver a entrar en los topes. Una respuesta que sola ya los
+    /// supera no se guarda
+    pub fn insert(&mut self, key: String, route: &str, response: String, ttl: Duration, now: Instant) {
+        self.remove(&key);
+        let tick = self.next_tick();
+        let entry = Entry { route: route.to_string(), response, stored: now, expires: now + ttl, tick };
+        let size = entry.bytes(&key);
+        if size > self.settings.max_bytes || self.settings.max_entries == 0 {
+            return;
+        }
+        self.bytes += size;
+        self.lru.insert(tick, key.clone());
+        self.entries.insert(key, entry);
+        self.stats_mut(route).stored += 1;
+
+        while self.entries.len() > self.settings.max_entries || self.bytes > self.settings.max_bytes {
+            let Some((_, oldest)) = self.lru.pop_first() else { break };
+            if let Some(e) = self.entries.remove(&oldest) {
+                self.bytes -= e.bytes(&oldest);
+                self.stats_mut(&e.route).evicted += 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.tick);
+        self.bytes -= entry.bytes(key);
+        Some(entry)
+    }
+
+    /// Vacía la caché entera o solo las respuestas de `route`
+    ///   - cuántas entradas se sacaron
+    pub fn flush(&mut self, route: Option<&str>) -> usize {
+        let keys: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| route.is_none_or(|r| e.route == r))
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in &keys {
+            self.remove(key);
+        }
+        keys.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn report(&self) -> CacheReport {
+        let mut totals = CacheStats::default();
+        for stats in self.stats.values() {
+            totals.merge(stats);
+        }
+        CacheReport {
+            enabled: self.settings.enabled,
+            entries: self.entries.len(),
+            bytes: self.bytes,
+            max_entries: self.settings.max_entries,
+            max_bytes: self.settings.max_bytes,
+            totals,
+            hit_ratio: totals.hit_ratio(),
+            routes: self
+                .stats
+                .iter()
+                .map(|(route, stats)| (route.clone(), RouteReport { stats: *stats, hit_ratio: stats.hit_ratio() }))
+                .collect(),
+        }
+    }
+
+    /// Las `limit` entradas usadas más recientemente
+    fn recent(&self, limit: usize, now: Instant) -> Vec<EntryReport> {
+        self.lru
+            .values()
+            .rev()
+            .take(limit)
+            .map(|key| {
+                let e = &self.entries[key];
+                EntryReport {
+                    route: e.route.clone(),
+                    key: key.chars().take(KEY_PREVIEW).collect(),
+                    bytes: e.bytes(key),
+                    age_secs: (now - e.stored).as_secs_f64(),
+                    expires_in_secs: e.expires.saturating_duration_since(now).as_secs_f64(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// true si la respuesta ya trae la cabecera `name`
+fn has_header(resp: &str, name: &str) -> bool {
+    let head = resp.split("\r\n\r\n").next().unwrap_or("");
+    head.lines().skip(1).any(|l| l.split_once(':').is_some_and(|(k, _)| k.eq_ignore_ascii_case(name)))
+}
+
+/// Middleware de ruta que guarda las respuestas 200 en la caché del
+/// servidor (`ServerState.cache`) durante `ttl` (o el configurado con
+/// `--cache-ttl`). Marca cada respuesta con `X-Cache`: HIT (con `Age`),
+/// MISS, o BYPASS si el cliente mandó `Cache-Control: no-cache` (se vuelve
+/// a calcular y se guarda) o `no-store` (tampoco se guarda). Va por fuera
+/// de `Singleflight`: solo el líder de un cálculo compartido la guarda
+pub struct CacheLayer {
+    ttl: Duration,
+}
+
+impl CacheLayer {
+    pub fn new(ttl: Duration) -> Self {
+        CacheLayer { ttl }
+    }
+}
+
+impl Middleware for CacheLayer {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        let control = req.header("Cache-Control").unwrap_or_default().to_ascii_lowercase();
+        let no_store = control.contains("no-store");
+        let bypass = no_store || control.contains("no-cache");
+        let key = Singleflight::key(req);
+        let ttl = {
+            let mut st = state.lock().unwrap();
+            if !st.cache.enabled() {
+                drop(st);
+                return next.run(req, state);
+            }
+            if bypass {
+                st.cache.bypass(&req.path);
+            } else if let Some((resp, age)) = st.cache.get(&key, &req.path, Instant::now()) {
+                let resp = http::with_header(resp, "Age", &age.as_secs().to_string());
+                return http::with_header(resp, "X-Cache", "HIT");
+            }
+            st.cache.ttl(&req.path, self.ttl)
+        };
+
+        let resp = next.run(req, state);
+        let storable = !no_store
+            && resp.starts_with("HTTP/1.0 200")
+            && !has_header(&resp, "X-Coalesced")
+            && !req.cancel.is_cancelled();
+        if storable {
+            state.lock().unwrap().cache.insert(key, &req.path, resp.clone(), ttl, Instant::now());
+        }
+        http::with_header(resp, "X-Cache", if bypass { "BYPASS" } else { "MISS" })
+    }
+}
+
+/// Parámetros de /admin/cache
+pub const CACHE_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("action", ParamKind::Text, "show", "show o flush").one_of(&["show", "flush"]),
+    ParamSpec::optional_no_default("route", ParamKind::Text, "vaciar solo las respuestas de esta ruta (flush)"),
+    ParamSpec::optional("limit", ParamKind::UInt, "20", "entradas a listar, las usadas más recientemente").range(None, Some(1000)),
+];
+
+/// Respuesta de /admin/cache: resumen y entradas más recientes
+#[derive(Serialize)]
+struct CacheAdminResponse {
+    #[serde(flatten)]
+    report: CacheReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flushed: Option<usize>,
+    recent: Vec<EntryReport>,
+}
+
+/// Muestra o vacía la caché de respuestas
+pub fn handle_cache(params: &Params, cache: &mut ResponseCache) -> String {
+    let flushed = match params.str("action") {
+        Some("flush") => {
+            let route = params.str("route");
+            let n = cache.flush(route);
+            println!("Admin: {} respuestas sacadas de la caché ({})", n, route.unwrap_or("todas las rutas"));
+            Some(n)
+        }
+        _ => None,
+    };
+    let body = CacheAdminResponse {
+        report: cache.report(),
+        flushed,
+        recent: cache.recent(params.usize("limit").unwrap_or(20), Instant::now()),
+    };
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        serde_json::to_string(&body).unwrap()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::new_state;
+    use std::sync::Arc;
+
+    fn small(max_entries: usize, max_bytes: usize) -> ResponseCache {
+        ResponseCache::new(CacheSettings { max_entries, max_bytes, ..CacheSettings::default() })
+    }
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let now = Instant::now();
+        let mut cache = small(2, 1 << 20);
+        cache.insert("a".into(), "/x", "A".into(), HOUR, now);
+        cache.insert("b".into(), "/x", "B".into(), HOUR, now);
+        // Usar "a" la vuelve la más reciente: la que sale es "b"
+        assert!(cache.get("a", "/x", now).is_some());
+        cache.insert("c".into(), "/x", "C".into(), HOUR, now);
+        assert!(cache.get("b", "/x", now).is_none());
+        assert_eq!(cache.get("a", "/x", now).unwrap().0, "A");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.report().totals.evicted, 1);
+
+        // Por bytes: 1 de clave + 4 de respuesta por entrada
+        let mut cache = small(10, 12);
+        cache.insert("a".into(), "/x", "AAAA".into(), HOUR, now);
+        cache.insert("b".into(), "/x", "BBBB".into(), HOUR, now);
+        cache.insert("c".into(), "/x", "CCCC".into(), HOUR, now);
+        assert_eq!((cache.len(), cache.report().bytes), (2, 10));
+        // Lo que no entra solo no se guarda
+        cache.insert("d".into(), "/x", "D".repeat(20), HOUR, now);
+        assert!(cache.get("d", "/x", now).is_none());
+    }
+
+    #[test]
+    fn expires_and_counts_hit_ratio() {
+        let now = Instant::now();
+        let mut cache = small(10, 1 << 20);
+        cache.insert("k".into(), "/x", "R".into(), Duration::from_secs(5), now);
+        let (_, age) = cache.get("k", "/x", now + Duration::from_secs(2)).unwrap();
+        assert_eq!(age, Duration::from_secs(2));
+        assert!(cache.get("k", "/x", now + Duration::from_secs(5)).is_none());
+        assert!(cache.is_empty());
+
+        let report = cache.report();
+        assert_eq!((report.totals.hits, report.totals.misses, report.totals.expired), (1, 1, 1));
+        assert_eq!(report.hit_ratio, 0.5);
+    }
+
+    #[test]
+    fn flush_by_route() {
+        let now = Instant::now();
+        let mut cache = small(10, 1 << 20);
+        cache.insert("a".into(), "/x", "A".into(), HOUR, now);
+        cache.insert("b".into(), "/y", "B".into(), HOUR, now);
+        assert_eq!(cache.flush(Some("/x")), 1);
+        assert!(cache.get("b", "/y", now).is_some());
+        assert_eq!(cache.flush(None), 1);
+        assert_eq!(cache.report().bytes, 0);
+    }
+
+    #[test]
+    fn parse_ttls_pairs() {
+        let ttls = parse_ttls("/hash:10m,/fibonacci:1.5s").unwrap();
+        assert_eq!(ttls["/hash"], Duration::from_secs(600));
+        assert_eq!(ttls["/fibonacci"], Duration::from_millis(1500));
+        assert!(parse_ttls("/hash").is_err());
+        assert!(parse_ttls("hash:1s").is_err());
+        assert!(parse_ttls("/hash:pronto").is_err());
+    }
+
+    #[test]
+    fn check_ttls_rejects_routes_without_cache() {
+        let cached: Vec<&str> = crate::router::cached_routes().into_iter().map(|(path, _)| path).collect();
+        let settings = |ttls: &str| CacheSettings { ttls: parse_ttls(ttls).unwrap(), ..CacheSettings::default() };
+        assert!(check_ttls(&settings("/hash:1m,/reverse:5s"), &cached).is_ok());
+        assert!(check_ttls(&settings("/status:1m"), &cached).unwrap_err().contains("'/status'"));
+        assert!(check_ttls(&settings("/fibonaci:60m"), &cached).is_err());
+    }
+
+    #[test]
+    fn layer_marks_hits_misses_and_bypass() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(CacheLayer::new(HOUR))];
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let endpoint = |req: &mut Request, _: &SharedState| {
+            let n = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            match req.query.as_str() {
+                "mal" => "HTTP/1.0 400 Bad Request\r\n\r\nmal".to_string(),
+                _ => format!("HTTP/1.0 200 OK\r\n\r\n{}", n),
+            }
+        };
+        let run = |req: &mut Request| Next::new(&chain, &endpoint).run(req, &state);
+
+        let miss = run(&mut Request::get("/x?a=1"));
+        assert!(miss.contains("X-Cache: MISS") && miss.ends_with("\r\n\r\n0"));
+        let hit = run(&mut Request::get("/x?a=1"));
+        assert!(hit.contains("X-Cache: HIT") && hit.contains("Age: 0") && hit.ends_with("\r\n\r\n0"));
+
+        // no-cache vuelve a calcular y refresca lo guardado
+        let mut fresh = Request::get("/x?a=1");
+        fresh.headers.push(("Cache-Control".into(), "no-cache".into()));
+        assert!(run(&mut fresh).contains("X-Cache: BYPASS"));
+        assert!(run(&mut Request::get("/x?a=1")).ends_with("\r\n\r\n1"));
+
+        // Los errores no se guardan
+        run(&mut Request::get("/x?mal"));
+        assert!(run(&mut Request::get("/x?mal")).contains("X-Cache: MISS"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        let st = state.lock().unwrap();
+        let stats = st.cache.report().routes["/x"].stats;
+        assert_eq!((stats.hits, stats.misses, stats.bypassed), (2, 3, 1));
+    }
+
+    #[test]
+    fn handle_cache_shows_and_flushes() {
+        let mut cache = small(10, 1 << 20);
+        cache.insert("GET /hash?text=a\n".into(), "/hash", "R".into(), HOUR, Instant::now());
+        let params = crate::registry::validate(CACHE_PARAMS, "").unwrap();
+        let resp = handle_cache(&params, &mut cache);
+        let body: serde_json::Value = serde_json::from_str(resp.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["entries"], 1);
+        assert_eq!(body["recent"][0]["route"], "/hash");
+
+        let params = crate::registry::validate(CACHE_PARAMS, "action=flush&route=/hash").unwrap();
+        let resp = handle_cache(&params, &mut cache);
+        assert!(resp.contains("\"flushed\":1"));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::http::Request;
+use crate::middleware::{Middleware, Next};
+use crate::registry::{ParamKind, ParamSpec, Params};
+use crate::state::SharedState;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+/// Ruta comodín: la regla se aplica a toda ruta sin regla propia
+pub const ANY_ROUTE: &str = "*";
+
+/// Probabilidades de cada falla para una ruta (cada una en [0, 1])
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct ChaosRule {
+    /// Probabilidad de añadir latencia
+    pub latency: f64,
+    /// Latencia máxima a añadir; se sortea en [0, latency_ms]
+    pub latency_ms: u64,
+    /// Probabilidad de responder 500 sin llamar al handler
+    pub error: f64,
+    /// Probabilidad de cerrar la conexión sin responder
+    pub drop: f64,
+    /// Probabilidad de cortar el cuerpo de la respuesta a la mitad
+    pub truncate: f64,
+    /// Probabilidad de hacer panic dentro del worker
+    pub panic: f64,
+}
+
+/// Cuántas fallas se han inyectado de cada tipo
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ChaosCounters {
+    pub latency: usize,
+    pub error: usize,
+    pub drop: usize,
+    pub truncate: usize,
+    pub panic: usize,
+}
+
+/// Fallas decididas para una petición
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Plan {
+    latency: Option<Duration>,
+    error: bool,
+    drop: bool,
+    truncate: bool,
+    panic: bool,
+}
+
+/// Configuración y estado del modo caos. El generador se siembra con `seed`,
+/// así la misma semilla y la misma secuencia de peticiones reproducen las
+/// mismas fallas
+pub struct Chaos {
+    seed: u64,
+    rng: StdRng,
+    rules: BTreeMap<String, ChaosRule>,
+    pub injected: ChaosCounters,
+}
+
+impl Chaos {
+    pub fn new(seed: u64) -> Self {
+        Chaos {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            rules: BTreeMap::new(),
+            injected: ChaosCounters::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reinicia el generador con una semilla nueva (y los contadores)
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.injected = ChaosCounters::default();
+    }
+
+    pub fn set_rule(&mut self, route: &str, rule: ChaosRule) {
+        self.rules.insert(route.to_string(), rule);
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    pub fn rules(&self) -> &BTreeMap<String, ChaosRule> {
+        &self.rules
+    }
+
+    fn rule_for(&self, path: &str) -> Option<ChaosRule> {
+        self.rules.get(path).or_else(|| self.rules.get(ANY_ROUTE)).copied()
+    }
+
+    /// Sortea las fallas de una petición. Siempre consume el mismo número de
+    /// valores del generador para que la secuencia no dependa del resultado
+    fn plan(&mut self, path: &str) -> Plan {
+        let Some(rule) = self.rule_for(path) else {
+            return Plan::default();
+        };
+        let rolls: [f64; 5] = self.rng.r#gen();
+        let latency_ms = self.rng.gen_range(0..=rule.latency_ms);
+
+        let plan = Plan {
+            latency: (rolls[0] < rule.latency).then(|| Duration::from_millis(latency_ms)),
+            error: rolls[1] < rule.error,
+            drop: rolls[2] < rule.drop,
+            truncate: rolls[3] < rule.truncate,
+            panic: rolls[4] < rule.panic,
+        };
+        let c = &mut self.injected;
+        c.latency += plan.latency.is_some() as usize;
+        c.error += plan.error as usize;
+        c.drop += plan.drop as usize;
+        c.truncate += plan.truncate as usize;
+        c.panic += plan.panic as usize;
+        plan
+    }
+}
+
+/// Corta el cuerpo de una respuesta a la mitad, dejando intactas las cabeceras
+fn truncate_body(resp: String) -> String {
+    match resp.find("\r\n\r\n") {
+        Some(i) => {
+            let body_start = i + 4;
+            let mut cut = body_start + (resp.len() - body_start) / 2;
+            while !resp.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            resp[..cut].to_string()
+        }
+        None => resp,
+    }
+}
+
+/// Middleware que inyecta las fallas configuradas en `ServerState.chaos`.
+/// Las rutas `/admin/...` nunca se ven afectadas, para poder apagarlo.
+/// Una respuesta vacía le indica al worker que cierre sin responder
+pub struct ChaosLayer;
+
+impl Middleware for ChaosLayer {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        if req.path.starts_with("/admin/") {
+            return next.run(req, state);
+        }
+        let plan = state.lock().unwrap().chaos.plan(&req.path);
+
+        if let Some(d) = plan.latency {
+            thread::sleep(d);
+        }
+        if plan.drop {
+            return String::new();
+        }
+        if plan.panic {
+            panic!("caos: panic inyectado en '{}'", req.path);
+        }
+        if plan.error {
+            return format!(
+                "HTTP/1.0 500 Internal Server Error\r\n\r\nCaos: error inyectado en '{}'\n",
+                req.path
+            );
+        }
+
+        let resp = next.run(req, state);
+        if plan.truncate { truncate_body(resp) } else { resp }
+    }
+}
+
+/// Parámetros de /admin/chaos
+pub const CHAOS_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("action", ParamKind::Text, "show", "show, set (fusiona con la regla actual) o clear (borra todas)")
+        .one_of(&["show", "set", "clear"]),
+    ParamSpec::optional("route", ParamKind::Text, ANY_ROUTE, "ruta a la que aplica la regla ('*' = todas)"),
+    ParamSpec::optional_no_default("latency", ParamKind::Float, "probabilidad de añadir latencia").range(Some(0), Some(1)),
+    ParamSpec::optional_no_default("latency_ms", ParamKind::UInt, "latencia máxima añadida en ms").range(Some(0), Some(60_000)),
+    ParamSpec::optional_no_default("error", ParamKind::Float, "probabilidad de responder 500").range(Some(0), Some(1)),
+    ParamSpec::optional_no_default("drop", ParamKind::Float, "probabilidad de cerrar sin responder").range(Some(0), Some(1)),
+    ParamSpec::optional_no_default("truncate", ParamKind::Float, "probabilidad de truncar el cuerpo").range(Some(0), Some(1)),
+    ParamSpec::optional_no_default("panic", ParamKind::Float, "probabilidad de panic en el worker").range(Some(0), Some(1)),
+    ParamSpec::optional_no_default("seed", ParamKind::UInt, "reinicia el generador con esta semilla"),
+];
+
+#[derive(Serialize)]
+struct ChaosResponse<'a> {
+    seed: u64,
+    rules: &'a BTreeMap<String, ChaosRule>,
+    injected: ChaosCounters,
+}
+
+/// Consulta o cambia la configuración del modo caos; siempre responde con
+/// el estado resultante (reglas, semilla y fallas inyectadas)
+pub fn handle_chaos(params: &Params, chaos: &mut Chaos) -> String {
+    if let Some(seed) = params.u64("seed") {
+        chaos.reseed(seed);
+    }
+    match params.str("action") {
+        Some("set") => {
+            let route = params.str("route").unwrap_or(ANY_ROUTE);
+            let mut rule = chaos.rules.get(route).copied().unwrap_or_default();
+            let prob = |name, current| params.f64(name).unwrap_or(current);
+            rule.latency = prob("latency", rule.latency);
+            rule.error = prob("error", rule.error);
+            rule.drop = prob("drop", rule.drop);
+            rule.truncate = prob("truncate", rule.truncate);
+            rule.panic = prob("panic", rule.panic);
+            rule.latency_ms = params.u64("latency_ms").unwrap_or(rule.latency_ms);
+            chaos.set_rule(route, rule);
+        }
+        Some("clear") => chaos.clear(),
+        _ => {}
+    }
+
+    let body = ChaosResponse { seed: chaos.seed, rules: &chaos.rules, injected: chaos.injected };
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        serde_json::to_string(&body).unwrap()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::new_state;
+    use std::sync::Arc;
+
+    fn ok(_: &mut Request, _: &SharedState) -> String {
+        "HTTP/1.0 200 OK\r\n\r\n0123456789".to_string()
+    }
+
+    #[test]
+    fn same_seed_same_failures() {
+        let rule = ChaosRule { latency: 0.3, latency_ms: 10, error: 0.3, drop: 0.3, truncate: 0.3, panic: 0.3 };
+        let run = |seed| {
+            let mut c = Chaos::new(seed);
+            c.set_rule(ANY_ROUTE, rule);
+            (0..50).map(|_| c.plan("/x")).collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn route_rule_overrides_wildcard() {
+        let mut c = Chaos::new(1);
+        c.set_rule(ANY_ROUTE, ChaosRule { error: 1.0, ..Default::default() });
+        c.set_rule("/ok", ChaosRule::default());
+        assert!(c.plan("/x").error);
+        assert!(!c.plan("/ok").error);
+        assert_eq!(c.injected.error, 1);
+    }
+
+    #[test]
+    fn layer_injects_errors_drops_and_truncation() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(ChaosLayer)];
+        let set = |rule| state.lock().unwrap().chaos.set_rule("/x", rule);
+
+        set(ChaosRule { error: 1.0, ..Default::default() });
+        let resp = Next::new(&chain, &ok).run(&mut Request::get("/x"), &state);
+        assert!(resp.starts_with("HTTP/1.0 500"));
+
+        set(ChaosRule { drop: 1.0, ..Default::default() });
+        assert_eq!(Next::new(&chain, &ok).run(&mut Request::get("/x"), &state), "");
+
+        set(ChaosRule { truncate: 1.0, ..Default::default() });
+        let resp = Next::new(&chain, &ok).run(&mut Request::get("/x"), &state);
+        assert!(resp.ends_with("\r\n\r\n01234"));
+
+        // Las rutas de administración no se tocan
+        set(ChaosRule { drop: 1.0, ..Default::default() });
+        state.lock().unwrap().chaos.set_rule(ANY_ROUTE, ChaosRule { drop: 1.0, ..Default::default() });
+        let resp = Next::new(&chain, &ok).run(&mut Request::get("/admin/chaos"), &state);
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn layer_panics_inside_worker() {
+        let state = new_state();
+        state.lock().unwrap().chaos.set_rule("/x", ChaosRule { panic: 1.0, ..Default::default() });
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(ChaosLayer)];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Next::new(&chain, &ok).run(&mut Request::get("/x"), &state)
+        }));
+        assert!(result.is_err());
+        // El lock del estado no queda envenenado
+        assert!(state.lock().is_ok());
+    }
+}
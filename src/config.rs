@@ -0,0 +1,314 @@
+use crate::cache::{parse_ttls, CacheSettings};
+use crate::frontend::{ConnSettings, Frontend};
+use crate::pool::ScalingSettings;
+use crate::scheduler::{PolicyKind, SchedulerSettings};
+use std::time::Duration;
+
+/// Ayuda de `--help`: todas las opciones con su valor por defecto
+pub const USAGE: &str = "\
+Uso: Proyecto_1 [--opcion=valor | --opcion valor]...
+
+  --addr=DIR                     dirección donde escuchar (127.0.0.1:8080)
+  --loadtest-max-tasks=N         tareas máximas de una petición a /loadtest (10000)
+  --loadtest-max-concurrency=N   hilos del executor de /loadtest (64)
+  --rate-limit=on|off            rate limiting por IP y ruta (on)
+  --coalesce=on|off              compartir cálculos idénticos en curso (on)
+  --cache=on|off                 caché de respuestas de las rutas deterministas (on)
+  --cache-entries=N              respuestas guardadas como máximo (1024)
+  --cache-bytes=N                bytes guardados como máximo (16777216)
+  --cache-ttl=RUTA:DUR,...       vencimiento por ruta cacheada, p. ej. /hash:10m
+  --chaos-seed=N                 semilla del modo caos (al azar)
+  --random-seed=N                semilla de servidor para /random (al azar)
+  --compute-timeout=DUR          tiempo máximo de los comandos de cálculo (10s)
+  --scheduler=fifo|sjf|priority|rr  política de las colas de los pools (fifo)
+  --quantum=DUR                  porción de tiempo de rr (50ms)
+  --aging=N                      puntos de prioridad por segundo de espera (1)
+  --shared-workers=N             workers iniciales del pool compartido (8)
+  --thread-budget=N              workers como máximo entre todos los pools (256)
+  --scale-queue-depth=N          un pool crece con más de N en cola por worker (4)
+  --scale-queue-wait=DUR         ...o si la más antigua espera más que esto (100ms)
+  --scale-idle=DUR               un pool se achica tras estar ocioso este tiempo (10s)
+  --frontend=events|blocking     cómo se leen las peticiones (events)
+  --header-timeout=DUR           plazo para recibir las cabeceras enteras (10s)
+  --body-timeout=DUR             plazo para el cuerpo, desde las cabeceras (30s)
+  --write-timeout=DUR            máximo que puede trabarse una escritura (30s)
+  --conn-idle-timeout=DUR        una conexión sin recibir ni un byte se corta (5s)
+  --max-connections=N            conexiones abiertas a la vez (10000)
+  --max-connections-per-ip=N     conexiones abiertas a la vez por IP (256)
+  --trace-file=ARCHIVO           volcar la traza de los workers al cerrar
+  --help                         mostrar esta ayuda
+";
+
+/// Configuración del servidor. Se toma de los argumentos de línea de
+/// comandos con la forma `--opcion=valor` (o `--opcion valor`)
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Dirección donde escucha el servidor
+    pub addr: String,
+    /// Máximo de tareas que acepta una sola petición a /loadtest
+    pub loadtest_max_tasks: usize,
+    /// Hilos del executor de /loadtest (techo de concurrencia)
+    pub loadtest_max_concurrency: usize,
+    /// Si es false el rate limiter deja pasar todo (útil al correr `bench`)
+    pub rate_limit: bool,
+    /// Si es false las rutas deterministas no comparten cálculos en curso
+    pub coalesce: bool,
+    /// Tamaño y vencimientos de la caché de respuestas de las rutas deterministas
+    pub cache: CacheSettings,
+    /// Semilla del modo caos; si no se indica se elige una al azar
+    pub chaos_seed: Option<u64>,
+    /// Semilla de servidor para /random: de ella salen las semillas de las
+    /// peticiones que no traen `seed`, así una corrida entera se repite
+    pub random_seed: Option<u64>,
+    /// Tiempo máximo de los comandos de cálculo (/isprime, /pi, ...); el
+    /// parámetro `timeout` de cada petición solo puede acortarlo
+    pub compute_timeout: Duration,
+    /// Política con la que cada pool toma peticiones de su cola
+    pub scheduler: PolicyKind,
+    /// Porción de tiempo de round-robin y puntos de aging por segundo
+    pub scheduler_settings: SchedulerSettings,
+    /// Workers iniciales del pool compartido (rutas sin pool propio)
+    pub shared_workers: usize,
+    /// Presupuesto global de hilos y umbrales del auto-escalado de los pools
+    pub scaling: ScalingSettings,
+    /// Cómo se leen las peticiones: bucle de eventos o lectura bloqueante
+    pub frontend: Frontend,
+    /// Plazos de lectura y escritura y topes de conexiones abiertas
+    pub connections: ConnSettings,
+    /// Archivo donde se vuelca la traza de los workers (Chrome Trace Event)
+    /// al cerrar el servidor
+    pub trace_file: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            addr: "127.0.0.1:8080".to_string(),
+            loadtest_max_tasks: 10_000,
+            loadtest_max_concurrency: 64,
+            rate_limit: true,
+            coalesce: true,
+            cache: CacheSettings::default(),
+            chaos_seed: None,
+            random_seed: None,
+            compute_timeout: Duration::from_secs(10),
+            scheduler: PolicyKind::Fifo,
+            scheduler_settings: SchedulerSettings::default(),
+            shared_workers: 8,
+            scaling: ScalingSettings::default(),
+            frontend: Frontend::Events,
+            connections: ConnSettings::default(),
+            trace_file: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parsea los argumentos (sin el nombre del programa)
+    ///   - Err si una opción es desconocida o su valor no es válido
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let Some(opt) = arg.strip_prefix("--") else {
+                return Err(format!("Argumento inesperado: '{}'", arg));
+            };
+            let (name, value) = match opt.split_once('=') {
+                Some((n, v)) => (n.to_string(), v.to_string()),
+                None => {
+                    let v = args
+                        .next()
+                        .ok_or_else(|| format!("Opción '--{}' requiere un valor", opt))?;
+                    (opt.to_string(), v)
+                }
+            };
+            config.set(&name, &value)?;
+        }
+
+        Ok(config)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "addr" => self.addr = value.to_string(),
+            "loadtest-max-tasks" => self.loadtest_max_tasks = positive(name, value)?,
+            "loadtest-max-concurrency" => self.loadtest_max_concurrency = positive(name, value)?,
+            "rate-limit" => self.rate_limit = on_off(name, value)?,
+            "coalesce" => self.coalesce = on_off(name, value)?,
+            "cache" => self.cache.enabled = on_off(name, value)?,
+            "cache-entries" => self.cache.max_entries = positive(name, value)?,
+            "cache-bytes" => self.cache.max_bytes = positive(name, value)?,
+            "cache-ttl" => self.cache.ttls = parse_ttls(value).map_err(|e| format!("Opción '--{}': {}", name, e))?,
+            "chaos-seed" => self.chaos_seed = Some(seed(name, value)?),
+            "random-seed" => self.random_seed = Some(seed(name, value)?),
+            "compute-timeout" => self.compute_timeout = duration(name, value)?,
+            "scheduler" => {
+                self.scheduler = PolicyKind::parse(value)
+                    .ok_or_else(|| format!("Opción '--{}' debe ser fifo, sjf, priority o rr", name))?
+            }
+            "quantum" => self.scheduler_settings.quantum = duration(name, value)?,
+            "shared-workers" => self.shared_workers = positive(name, value)?,
+            "thread-budget" => self.scaling.thread_budget = positive(name, value)?,
+            "scale-queue-depth" => self.scaling.queue_depth = positive(name, value)?,
+            "scale-queue-wait" => self.scaling.queue_wait = duration(name, value)?,
+            "scale-idle" => self.scaling.idle = duration(name, value)?,
+            "frontend" => {
+                self.frontend = Frontend::parse(value)
+                    .ok_or_else(|| format!("Opción '--{}' debe ser events o blocking", name))?
+            }
+            "header-timeout" => self.connections.header_timeout = duration(name, value)?,
+            "body-timeout" => self.connections.body_timeout = duration(name, value)?,
+            "write-timeout" => self.connections.write_timeout = duration(name, value)?,
+            "conn-idle-timeout" => self.connections.idle_timeout = duration(name, value)?,
+            "max-connections" => self.connections.max_connections = positive(name, value)?,
+            "max-connections-per-ip" => self.connections.max_per_ip = positive(name, value)?,
+            "trace-file" => self.trace_file = Some(value.to_string()),
+            "aging" => self.scheduler_settings.aging_per_sec = non_negative(name, value)?,
+            _ => return Err(format!("Opción desconocida: '--{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+fn positive(name: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Opción '--{}' debe ser un entero positivo", name)),
+    }
+}
+
+fn duration(name: &str, value: &str) -> Result<Duration, String> {
+    match crate::registry::parse_duration(value) {
+        Ok(d) if !d.is_zero() => Ok(d),
+        _ => Err(format!("Opción '--{}' debe ser una duración positiva (p. ej. 250ms, 1.5s, 2m)", name)),
+    }
+}
+
+fn non_negative(name: &str, value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(x) if x.is_finite() && x >= 0.0 => Ok(x),
+        _ => Err(format!("Opción '--{}' debe ser un número no negativo", name)),
+    }
+}
+
+fn seed(name: &str, value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Opción '--{}' debe ser un entero no negativo", name))
+}
+
+fn on_off(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(format!("Opción '--{}' debe ser on u off", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn from_args_both_forms() {
+        let c = Config::from_args(args(&["--loadtest-max-tasks=50", "--addr", "0.0.0.0:9000"])).unwrap();
+        assert_eq!(c.loadtest_max_tasks, 50);
+        assert_eq!(c.addr, "0.0.0.0:9000");
+        assert_eq!(c.loadtest_max_concurrency, Config::default().loadtest_max_concurrency);
+        assert_eq!(
+            Config::from_args(args(&["--compute-timeout=1.5s"])).unwrap().compute_timeout,
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn from_args_rate_limit_coalesce_and_cache() {
+        let c = Config::from_args(args(&["--rate-limit=off"])).unwrap();
+        assert!(!c.rate_limit);
+        assert!(c.coalesce);
+        assert!(c.cache.enabled);
+        assert!(!Config::from_args(args(&["--coalesce=off"])).unwrap().coalesce);
+        assert!(!Config::from_args(args(&["--cache=off"])).unwrap().cache.enabled);
+        let c = Config::from_args(args(&["--cache-entries=10", "--cache-bytes=4096", "--cache-ttl=/hash:1m"])).unwrap();
+        assert_eq!((c.cache.max_entries, c.cache.max_bytes), (10, 4096));
+        assert_eq!(c.cache.ttls["/hash"], Duration::from_secs(60));
+    }
+
+    #[test]
+    fn from_args_seeds() {
+        assert_eq!(Config::default().chaos_seed, None);
+        assert_eq!(Config::from_args(args(&["--chaos-seed=42"])).unwrap().chaos_seed, Some(42));
+        assert_eq!(Config::from_args(args(&["--random-seed", "7"])).unwrap().random_seed, Some(7));
+    }
+
+    #[test]
+    fn from_args_scheduler() {
+        let c = Config::from_args(args(&["--scheduler=rr", "--quantum=20ms", "--aging", "0.5"])).unwrap();
+        assert_eq!(c.scheduler, PolicyKind::RoundRobin);
+        assert_eq!(c.scheduler_settings.quantum, Duration::from_millis(20));
+        assert_eq!(c.scheduler_settings.aging_per_sec, 0.5);
+    }
+
+    #[test]
+    fn from_args_scaling() {
+        let c = Config::from_args(args(&["--thread-budget=32", "--scale-queue-wait=20ms", "--scale-idle=2s"])).unwrap();
+        assert_eq!(c.scaling.thread_budget, 32);
+        assert_eq!(c.scaling.queue_wait, Duration::from_millis(20));
+        assert_eq!(c.scaling.idle, Duration::from_secs(2));
+        assert_eq!(c.scaling.queue_depth, ScalingSettings::default().queue_depth);
+        assert_eq!(Config::from_args(args(&["--shared-workers=3"])).unwrap().shared_workers, 3);
+    }
+
+    #[test]
+    fn from_args_frontend_and_connections() {
+        assert_eq!(Config::default().frontend, Frontend::Events);
+        assert_eq!(Config::from_args(args(&["--frontend=blocking"])).unwrap().frontend, Frontend::Blocking);
+        let c = Config::from_args(args(&["--header-timeout=2s", "--conn-idle-timeout=500ms", "--max-connections-per-ip=4"]))
+            .unwrap();
+        assert_eq!(c.connections.header_timeout, Duration::from_secs(2));
+        assert_eq!(c.connections.idle_timeout, Duration::from_millis(500));
+        assert_eq!(c.connections.max_per_ip, 4);
+        assert_eq!(c.connections.body_timeout, ConnSettings::default().body_timeout);
+    }
+
+    #[test]
+    fn usage_lists_every_option() {
+        for opt in ["--scale-idle=", "--conn-idle-timeout=", "--cache-ttl=", "--max-connections-per-ip="] {
+            assert!(USAGE.contains(opt), "{}", opt);
+        }
+    }
+
+    #[test]
+    fn from_args_trace() {
+        assert_eq!(Config::default().trace_file, None);
+        assert_eq!(
+            Config::from_args(args(&["--trace-file=traza.json"])).unwrap().trace_file.as_deref(),
+            Some("traza.json")
+        );
+    }
+
+    #[test]
+    fn from_args_errors() {
+        assert!(Config::from_args(args(&["--nope=1"])).is_err());
+        assert!(Config::from_args(args(&["--loadtest-max-tasks=0"])).is_err());
+        assert!(Config::from_args(args(&["--addr"])).is_err());
+        assert!(Config::from_args(args(&["suelto"])).is_err());
+        assert!(Config::from_args(args(&["--rate-limit=maybe"])).is_err());
+        assert!(Config::from_args(args(&["--chaos-seed=-1"])).is_err());
+        assert!(Config::from_args(args(&["--compute-timeout=0"])).is_err());
+        assert!(Config::from_args(args(&["--scheduler=lifo"])).is_err());
+        assert!(Config::from_args(args(&["--aging=-1"])).is_err());
+        assert!(Config::from_args(args(&["--thread-budget=0"])).is_err());
+        // La de los pools es --scale-idle, para no confundirla con --conn-idle-timeout
+        assert!(Config::from_args(args(&["--idle-timeout=2s"])).is_err());
+        assert!(Config::from_args(args(&["--frontend=epoll"])).is_err());
+        assert!(Config::from_args(args(&["--write-timeout=0s"])).is_err());
+        assert!(Config::from_args(args(&["--max-connections=0"])).is_err());
+        assert!(Config::from_args(args(&["--cache-ttl=/hash"])).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+/// Tiempo de CPU consumido por el hilo actual, con el reloj
+/// `CLOCK_THREAD_CPUTIME_ID` (sin leer archivos); `None` si falla
+#[cfg(unix)]
+pub fn thread_cpu_time() -> Option<Duration> {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` es un timespec propio y válido donde escribe el kernel
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 {
+        return None;
+    }
+    Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+/// Sin ese reloj no se puede medir
+#[cfg(not(unix))]
+pub fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// CPU del hilo actual consumida desde `since` (cero si no se puede medir)
+pub fn cpu_since(since: Option<Duration>) -> Duration {
+    match (since, thread_cpu_time()) {
+        (Some(a), Some(b)) => b.saturating_sub(a),
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn busy_loop_consumes_cpu_time() {
+        let before = thread_cpu_time();
+        assert_eq!(before.is_some(), cfg!(unix));
+        let start = Instant::now();
+        let mut x = 0u64;
+        while start.elapsed() < Duration::from_millis(30) {
+            x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(7));
+        }
+        if before.is_some() {
+            assert!(cpu_since(before) >= Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::net::TcpStream;
+
+/// true si la conexión se cayó: el cliente la reseteó o dio error. No
+/// bloquea ni cambia el modo del socket (lo comparten el worker y el
+/// vigilante). Un fin de lectura no cuenta: tras mandar la petición un
+/// cliente HTTP/1.0 puede hacer `shutdown(Write)` y seguir esperando la
+/// respuesta (`nc -N`), y eso no se distingue de un cierre completo hasta
+/// que falla una escritura
+#[cfg(unix)]
+pub fn client_gone(stream: &TcpStream) -> bool {
+    use std::os::fd::AsRawFd;
+
+    // POLLHUP y POLLERR se informan siempre, sin pedirlos en `events`
+    let mut pfd = libc::pollfd { fd: stream.as_raw_fd(), events: 0, revents: 0 };
+    // SAFETY: `pfd` es un único pollfd válido y el timeout 0 no bloquea
+    if unsafe { libc::poll(&mut pfd, 1, 0) } <= 0 {
+        return false;
+    }
+    pfd.revents & (libc::POLLHUP | libc::POLLERR) != 0
+}
+
+/// Sin `poll` no se puede saber sin bloquear: se asume que sigue ahí
+#[cfg(not(unix))]
+pub fn client_gone(_: &TcpStream) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{Shutdown, TcpListener};
+
+    /// Cierra con RST en vez de FIN (SO_LINGER con plazo 0)
+    #[cfg(unix)]
+    fn reset(stream: TcpStream) {
+        use std::os::fd::AsRawFd;
+        let linger = libc::linger { l_onoff: 1, l_linger: 0 };
+        // SAFETY: `linger` es un valor propio del tamaño que se indica
+        let set = unsafe {
+            libc::setsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_LINGER,
+                (&linger as *const libc::linger).cast(),
+                std::mem::size_of::<libc::linger>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(set, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detects_reset_client_but_not_half_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        // Datos pendientes sin leer no cuentan como cierre
+        client.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        assert!(!client_gone(&server));
+
+        // Dejar de escribir no es irse: todavía espera la respuesta
+        client.shutdown(Shutdown::Write).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!client_gone(&server));
+
+        reset(client);
+        let start = std::time::Instant::now();
+        while !client_gone(&server) {
+            assert!(start.elapsed().as_secs() < 2, "no se detectó el cierre");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+}
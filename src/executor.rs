@@ -0,0 +1,114 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Pool interno de tamaño fijo para trabajo que antes lanzaba un hilo por
+/// tarea (/loadtest). Nunca crea más de `size` hilos
+pub struct Executor {
+    size: usize,
+    tx: Mutex<Sender<Job>>,
+}
+
+impl Executor {
+    pub fn new(name: &str, size: usize) -> Self {
+        let size = size.max(1);
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+
+        for i in 0..size {
+            let rx: Arc<Mutex<Receiver<Job>>> = rx.clone();
+            thread::Builder::new()
+                .name(format!("{}-{}", name, i))
+                .spawn(move || loop {
+                    // El lock se suelta antes de ejecutar el trabajo
+                    let job = rx.lock().unwrap().recv();
+                    match job {
+                        // Un panic en un trabajo no debe costar un hilo del pool
+                        Ok(job) => {
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => break,
+                    }
+                })
+                .expect("no se pudo crear hilo del executor");
+        }
+
+        Executor { size, tx: Mutex::new(tx) }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.tx.lock().unwrap().send(Box::new(job));
+    }
+
+    /// Ejecuta `tasks` tareas con a lo sumo `concurrency` en paralelo (y nunca
+    /// más que los hilos del executor). Espera a que terminen todas y devuelve
+    /// el máximo de tareas que llegaron a correr a la vez
+    pub fn run_batch<F>(&self, tasks: usize, concurrency: usize, task: F) -> usize
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        let runners = concurrency.min(self.size).min(tasks).max(1);
+        let task = Arc::new(task);
+        let next = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+
+        for _ in 0..runners {
+            let (task, next, running, peak, done_tx) =
+                (task.clone(), next.clone(), running.clone(), peak.clone(), done_tx.clone());
+            self.execute(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= tasks {
+                        break;
+                    }
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    task(i);
+                    running.fetch_sub(1, Ordering::SeqCst);
+                }
+                let _ = done_tx.send(());
+            });
+        }
+        drop(done_tx);
+
+        // Si un runner hace panic su Sender se suelta igual y no bloquea
+        for _ in done_rx {}
+        peak.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn run_batch_respects_concurrency() {
+        let ex = Executor::new("test", 4);
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let peak = ex.run_batch(12, 2, move |_| {
+            c.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+        });
+        assert_eq!(count.load(Ordering::SeqCst), 12);
+        assert!((1..=2).contains(&peak));
+    }
+
+    #[test]
+    fn run_batch_capped_by_executor_size() {
+        let ex = Executor::new("test", 3);
+        let peak = ex.run_batch(30, 100, |_| thread::sleep(Duration::from_millis(5)));
+        assert!(peak <= 3);
+    }
+}
@@ -0,0 +1,623 @@
+use crate::http::Request;
+use crate::state::SharedState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Tamaño máximo de la línea de petición más las cabeceras
+pub const MAX_HEAD: usize = 16 * 1024;
+
+/// Tamaño máximo del cuerpo de una petición
+pub const MAX_BODY: usize = 1024 * 1024;
+
+/// Cómo se leen las peticiones antes de pasarlas a los pools
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frontend {
+    /// Un bucle con epoll sobre sockets no bloqueantes: junta de a pedazos
+    /// las peticiones de muchas conexiones y despacha solo las completas.
+    /// Solo en Linux; en otros sistemas se usa `Blocking`
+    Events,
+    /// El diseño original: el hilo de aceptación lee cada conexión con
+    /// `read` bloqueante, así que un cliente lento frena a los demás
+    Blocking,
+}
+
+impl Frontend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Frontend::Events => "events",
+            Frontend::Blocking => "blocking",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Frontend> {
+        [Frontend::Events, Frontend::Blocking].into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// Plazos y topes de las conexiones de los clientes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnSettings {
+    /// Desde que se acepta la conexión hasta recibir la línea vacía de las cabeceras
+    pub header_timeout: Duration,
+    /// Desde el fin de las cabeceras hasta completar el cuerpo
+    pub body_timeout: Duration,
+    /// Máximo que puede trabarse una escritura de la respuesta
+    pub write_timeout: Duration,
+    /// Tiempo sin recibir ni un byte mientras se espera la petición
+    pub idle_timeout: Duration,
+    /// Conexiones abiertas a la vez (esperando petición o siendo atendidas)
+    pub max_connections: usize,
+    /// Conexiones abiertas a la vez desde una misma IP
+    pub max_per_ip: usize,
+}
+
+impl Default for ConnSettings {
+    fn default() -> Self {
+        ConnSettings {
+            header_timeout: Duration::from_secs(10),
+            body_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(5),
+            max_connections: 10_000,
+            max_per_ip: 256,
+        }
+    }
+}
+
+/// Motivo por el que se cortó una conexión
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    HeaderTimeout,
+    BodyTimeout,
+    WriteTimeout,
+    IdleTimeout,
+    MaxConnections,
+    MaxPerIp,
+}
+
+/// Conexiones cortadas por cada motivo (para /status)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ConnViolations {
+    pub header_timeout: usize,
+    pub body_timeout: usize,
+    pub write_timeout: usize,
+    pub idle_timeout: usize,
+    pub max_connections: usize,
+    pub max_per_ip: usize,
+}
+
+impl ConnViolations {
+    pub fn record(&mut self, v: Violation) {
+        let counter = match v {
+            Violation::HeaderTimeout => &mut self.header_timeout,
+            Violation::BodyTimeout => &mut self.body_timeout,
+            Violation::WriteTimeout => &mut self.write_timeout,
+            Violation::IdleTimeout => &mut self.idle_timeout,
+            Violation::MaxConnections => &mut self.max_connections,
+            Violation::MaxPerIp => &mut self.max_per_ip,
+        };
+        *counter += 1;
+    }
+}
+
+/// Respuesta a quien no completó su petición a tiempo
+pub const REQUEST_TIMEOUT: &str = "HTTP/1.0 408 Request Timeout\r\n\r\nLa petición no llegó a tiempo\n";
+
+/// Cuenta las conexiones abiertas, en total y por IP, para aplicar los topes
+#[derive(Debug)]
+pub struct ConnTracker {
+    max_connections: usize,
+    max_per_ip: usize,
+    open: Mutex<(usize, HashMap<IpAddr, usize>)>,
+}
+
+impl ConnTracker {
+    pub fn new(settings: &ConnSettings) -> Arc<Self> {
+        Arc::new(ConnTracker {
+            max_connections: settings.max_connections,
+            max_per_ip: settings.max_per_ip,
+            open: Mutex::new((0, HashMap::new())),
+        })
+    }
+
+    /// Reserva un lugar para una conexión nueva de `ip`
+    ///   - Err con el tope que se superó
+    pub fn open(self: &Arc<Self>, ip: IpAddr) -> Result<ConnSlot, Violation> {
+        let mut open = self.open.lock().unwrap();
+        if open.0 >= self.max_connections {
+            return Err(Violation::MaxConnections);
+        }
+        let per_ip = open.1.entry(ip).or_default();
+        if *per_ip >= self.max_per_ip {
+            return Err(Violation::MaxPerIp);
+        }
+        *per_ip += 1;
+        open.0 += 1;
+        Ok(ConnSlot { tracker: self.clone(), ip })
+    }
+
+    /// Conexiones abiertas en este momento
+    pub fn count(&self) -> usize {
+        self.open.lock().unwrap().0
+    }
+}
+
+/// Lugar reservado por una conexión; se libera al soltarlo (cuando se
+/// descarta la conexión o termina la tarea que la atiende)
+#[derive(Debug)]
+pub struct ConnSlot {
+    tracker: Arc<ConnTracker>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnSlot {
+    fn drop(&mut self) {
+        let mut open = self.tracker.open.lock().unwrap();
+        open.0 -= 1;
+        if let Some(n) = open.1.get_mut(&self.ip) {
+            *n -= 1;
+            if *n == 0 {
+                open.1.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Qué tan completa está la petición acumulada en el buffer de una conexión
+#[derive(Debug)]
+pub enum Progress {
+    /// Faltan bytes de las cabeceras
+    Partial,
+    /// Las cabeceras están completas pero falta parte del cuerpo
+    Body,
+    Complete(Box<Request>),
+    /// No se puede atender: respuesta de error para el cliente
+    Invalid(String),
+}
+
+/// Largo del cuerpo según `Content-Length` (0 si no viene)
+///   - Err con la respuesta 400/413 si el largo es inválido o excesivo
+pub fn body_len(request: &Request) -> Result<usize, String> {
+    let Some(len) = request.header("Content-Length") else {
+        return Ok(0);
+    };
+    let len: usize = len
+        .parse()
+        .map_err(|_| "HTTP/1.0 400 Bad Request\r\n\r\nContent-Length inválido\n".to_string())?;
+    if len > MAX_BODY {
+        return Err(format!(
+            "HTTP/1.0 413 Payload Too Large\r\n\r\nEl cuerpo no puede superar {} bytes\n",
+            MAX_BODY
+        ));
+    }
+    Ok(len)
+}
+
+/// Revisa si `buf` ya tiene una petición entera (cabeceras hasta la línea
+/// vacía y tantos bytes de cuerpo como diga `Content-Length`). Con `eof` el
+/// cliente ya no va a mandar más (cerró su lado de escritura, como `nc -N`,
+/// pero sigue esperando la respuesta): una petición completa se despacha
+/// igual y una a medias recibe 400
+pub fn parse_buffered(buf: &[u8], eof: bool) -> Progress {
+    let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+        if buf.len() > MAX_HEAD {
+            return Progress::Invalid(format!(
+                "HTTP/1.0 431 Request Header Fields Too Large\r\n\r\nLas cabeceras no pueden superar {} bytes\n",
+                MAX_HEAD
+            ));
+        }
+        if !eof {
+            return Progress::Partial;
+        }
+        return Progress::Invalid("HTTP/1.0 400 Bad Request\r\n\r\nCabeceras incompletas\n".to_string());
+    };
+    let mut request = match Request::parse(buf) {
+        Ok(r) => r,
+        Err(_) => return Progress::Invalid(BAD_REQUEST.to_string()),
+    };
+    let len = match body_len(&request) {
+        Ok(len) => len,
+        Err(resp) => return Progress::Invalid(resp),
+    };
+    if buf.len() - end - 4 < len {
+        if eof {
+            return Progress::Invalid("HTTP/1.0 400 Bad Request\r\n\r\nCuerpo incompleto\n".to_string());
+        }
+        return Progress::Body;
+    }
+    request.body.truncate(len);
+    Progress::Complete(Box::new(request))
+}
+
+const BAD_REQUEST: &str = "HTTP/1.0 400 Bad Request\r\n\r\nBad Request";
+
+/// Conexión aceptada cuya petición todavía no llegó entera
+struct Conn {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    slot: ConnSlot,
+    accepted: Instant,
+    last_read: Instant,
+    /// Cuándo se completaron las cabeceras (desde ahí corre el plazo del cuerpo)
+    head_at: Option<Instant>,
+}
+
+impl Conn {
+    fn new(stream: TcpStream, slot: ConnSlot) -> Self {
+        let now = Instant::now();
+        Conn { stream, buf: Vec::new(), slot, accepted: now, last_read: now, head_at: None }
+    }
+
+    /// Lee todo lo disponible sin bloquear
+    ///   - Ok(true) si el cliente cerró su lado de la conexión
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(true),
+                Ok(n) => {
+                    self.buf.extend_from_slice(&chunk[..n]);
+                    self.last_read = Instant::now();
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Plazo vencido a `now`, si hay alguno
+    fn expired(&self, now: Instant, settings: &ConnSettings) -> Option<Violation> {
+        if now - self.last_read >= settings.idle_timeout {
+            return Some(Violation::IdleTimeout);
+        }
+        match self.head_at {
+            None if now - self.accepted >= settings.header_timeout => Some(Violation::HeaderTimeout),
+            Some(at) if now - at >= settings.body_timeout => Some(Violation::BodyTimeout),
+            _ => None,
+        }
+    }
+}
+
+/// Cada cuánto se revisan los plazos de las conexiones a la espera
+const SWEEP: Duration = Duration::from_millis(100);
+
+/// Eventos que se piden a `epoll_wait` por vuelta
+#[cfg(target_os = "linux")]
+const MAX_EVENTS: usize = 1024;
+
+/// Registra (`EPOLL_CTL_ADD`) o quita (`EPOLL_CTL_DEL`) un descriptor del epoll
+#[cfg(target_os = "linux")]
+fn epoll_ctl(epfd: std::os::fd::RawFd, op: libc::c_int, fd: std::os::fd::RawFd) -> io::Result<()> {
+    let mut ev = libc::epoll_event { events: (libc::EPOLLIN | libc::EPOLLRDHUP) as u32, u64: fd as u64 };
+    // SAFETY: `ev` vive durante la llamada; el kernel solo lo lee
+    if unsafe { libc::epoll_ctl(epfd, op, fd, &mut ev) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Atiende el listener con un solo hilo y epoll: acepta todas las
+/// conexiones que lleguen, acumula lo que mandan sin bloquear y le pasa a
+/// `on_request` cada petición completa con su socket ya en modo bloqueante
+/// (los workers escriben la respuesta con `write_all`) y su lugar entre las
+/// conexiones abiertas. Cada vuelta cuesta según las conexiones con datos,
+/// no según las abiertas, así que miles de clientes callados no frenan a
+/// los demás. Las conexiones a la espera se publican en
+/// `ServerState.pending_connections`; las que superan los topes o los
+/// plazos de `config.connections` se cortan (con 408 si mandaron algo) y se
+/// cuentan en `ServerState.connection_violations`
+#[cfg(target_os = "linux")]
+pub fn serve(
+    listener: &TcpListener,
+    state: &SharedState,
+    mut on_request: impl FnMut(TcpStream, Request, ConnSlot),
+) -> io::Result<()> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+    let (settings, tracker) = {
+        let st = state.lock().unwrap();
+        (st.config.connections, st.connections.clone())
+    };
+    listener.set_nonblocking(true)?;
+    // SAFETY: epoll_create1 no toma punteros; el descriptor se cierra con el OwnedFd
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    if epfd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `epfd` es un descriptor recién creado y solo lo posee este OwnedFd
+    let epoll = unsafe { OwnedFd::from_raw_fd(epfd) };
+    let epfd = epoll.as_raw_fd();
+    let listen_fd = listener.as_raw_fd();
+    epoll_ctl(epfd, libc::EPOLL_CTL_ADD, listen_fd)?;
+
+    let mut conns: HashMap<RawFd, Conn> = HashMap::new();
+    let mut events = vec![libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+    let mut published = 0;
+    let mut last_sweep = Instant::now();
+
+    loop {
+        // SAFETY: `events` tiene lugar para MAX_EVENTS eventos
+        let n = unsafe {
+            libc::epoll_wait(epfd, events.as_mut_ptr(), MAX_EVENTS as libc::c_int, SWEEP.as_millis() as libc::c_int)
+        };
+        if n < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        for ev in &events[..n as usize] {
+            let fd = ev.u64 as RawFd;
+            if fd == listen_fd {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, peer)) => {
+                            let slot = match tracker.open(peer.ip()) {
+                                Ok(slot) => slot,
+                                Err(v) => {
+                                    state.lock().unwrap().connection_violations.record(v);
+                                    continue;
+                                }
+                            };
+                            let fd = stream.as_raw_fd();
+                            if stream.set_nonblocking(true).is_ok() && epoll_ctl(epfd, libc::EPOLL_CTL_ADD, fd).is_ok() {
+                                conns.insert(fd, Conn::new(stream, slot));
+                            }
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        Err(e) => {
+                            // P. ej. sin descriptores libres: se reintenta en la próxima vuelta
+                            eprintln!("Error en la conexión: {}", e);
+                            std::thread::sleep(std::time::Duration::from_millis(10));
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let Some(conn) = conns.get_mut(&fd) else { continue };
+            let progress = match conn.fill() {
+                Ok(eof) if eof && conn.buf.is_empty() => None,
+                Ok(eof) => Some(parse_buffered(&conn.buf, eof)),
+                Err(_) => None,
+            };
+            match progress {
+                Some(Progress::Partial) => continue,
+                Some(Progress::Body) => {
+                    conn.head_at.get_or_insert_with(Instant::now);
+                    continue;
+                }
+                _ => {}
+            }
+            // Se saca del epoll antes de entregarla: el worker clona el
+            // socket y el registro sobreviviría al cierre de este descriptor
+            let _ = epoll_ctl(epfd, libc::EPOLL_CTL_DEL, fd);
+            let mut conn = conns.remove(&fd).unwrap();
+            match progress {
+                Some(Progress::Complete(request)) if conn.stream.set_nonblocking(false).is_ok() => {
+                    on_request(conn.stream, *request, conn.slot);
+                }
+                Some(Progress::Invalid(resp)) => {
+                    let _ = conn.stream.write_all(resp.as_bytes());
+                }
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        if now - last_sweep >= SWEEP {
+            last_sweep = now;
+            let expired: Vec<(RawFd, Violation)> =
+                conns.iter().filter_map(|(fd, c)| c.expired(now, &settings).map(|v| (*fd, v))).collect();
+            for (fd, v) in expired {
+                let _ = epoll_ctl(epfd, libc::EPOLL_CTL_DEL, fd);
+                let mut conn = conns.remove(&fd).unwrap();
+                // Quien no mandó nada se corta sin respuesta
+                if !conn.buf.is_empty() {
+                    let _ = conn.stream.write_all(REQUEST_TIMEOUT.as_bytes());
+                }
+                state.lock().unwrap().connection_violations.record(v);
+            }
+        }
+
+        if conns.len() != published {
+            published = conns.len();
+            state.lock().unwrap().pending_connections = published;
+        }
+    }
+}
+
+/// Sin epoll se atiende con la lectura bloqueante
+#[cfg(not(target_os = "linux"))]
+pub fn serve(_: &TcpListener, _: &SharedState, _: impl FnMut(TcpStream, Request, ConnSlot)) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "el frontend por eventos requiere epoll"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn complete(buf: &[u8], eof: bool) -> Request {
+        match parse_buffered(buf, eof) {
+            Progress::Complete(r) => *r,
+            other => panic!("se esperaba una petición completa: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_buffered_waits_for_head_and_body() {
+        assert!(matches!(parse_buffered(b"GET /status HT", false), Progress::Partial));
+        assert_eq!(complete(b"GET /status HTTP/1.0\r\n\r\n", false).path, "/status");
+
+        let post = b"POST /reverse HTTP/1.0\r\nContent-Length: 5\r\n\r\nhol";
+        assert!(matches!(parse_buffered(post, false), Progress::Body));
+        let mut post = post.to_vec();
+        post.extend_from_slice(b"a!extra");
+        assert_eq!(complete(&post, false).body, b"hola!");
+
+        // Cerrar tras las cabeceras completas no cambia nada
+        assert_eq!(complete(b"GET /help HTTP/1.0\r\n\r\n", true).path, "/help");
+    }
+
+    #[test]
+    fn parse_buffered_rejects_invalid() {
+        let invalid = |buf: &[u8], eof: bool| match parse_buffered(buf, eof) {
+            Progress::Invalid(resp) => resp,
+            other => panic!("se esperaba un error: {:?}", other),
+        };
+        assert!(invalid(b"\r\n\r\n", false).starts_with("HTTP/1.0 400"));
+        assert!(invalid(b"POST / HTTP/1.0\r\nContent-Length: x\r\n\r\n", false).starts_with("HTTP/1.0 400"));
+        let huge = format!("POST / HTTP/1.0\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        assert!(invalid(huge.as_bytes(), false).starts_with("HTTP/1.0 413"));
+        assert!(invalid(&vec![b'a'; MAX_HEAD + 1], false).starts_with("HTTP/1.0 431"));
+        assert!(invalid(b"POST / HTTP/1.0\r\nContent-Length: 9\r\n\r\nab", true).contains("Cuerpo incompleto"));
+        assert!(invalid(b"GET /help HTTP/1.0\r\n", true).contains("Cabeceras incompletas"));
+    }
+
+    #[test]
+    fn tracker_caps_total_and_per_ip() {
+        let settings = ConnSettings { max_connections: 3, max_per_ip: 2, ..ConnSettings::default() };
+        let tracker = ConnTracker::new(&settings);
+        let (a, b): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+
+        let a1 = tracker.open(a).unwrap();
+        let _a2 = tracker.open(a).unwrap();
+        assert_eq!(tracker.open(a).unwrap_err(), Violation::MaxPerIp);
+        let _b1 = tracker.open(b).unwrap();
+        assert_eq!(tracker.open(b).unwrap_err(), Violation::MaxConnections);
+        assert_eq!(tracker.count(), 3);
+
+        // Al soltar un lugar lo puede usar cualquiera
+        drop(a1);
+        assert_eq!(tracker.count(), 2);
+        let _b2 = tracker.open(b).unwrap();
+    }
+
+    /// Arranca el bucle de eventos con `config`; las peticiones completas
+    /// llegan por el canal
+    #[cfg(target_os = "linux")]
+    fn spawn_loop(config: crate::config::Config) -> (std::net::SocketAddr, SharedState, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = crate::state::new_state_with(crate::router::builtin_registry(), config);
+        let (tx, rx) = mpsc::channel();
+        let loop_state = state.clone();
+        std::thread::spawn(move || {
+            let _ = serve(&listener, &loop_state, |_, req, _| tx.send(req.path).unwrap());
+        });
+        (addr, state, rx)
+    }
+
+    /// Lo que responde el servidor hasta cerrar la conexión
+    #[cfg(target_os = "linux")]
+    fn read_reply(mut stream: TcpStream) -> String {
+        stream.set_read_timeout(Some(Duration::from_secs(3))).unwrap();
+        let mut out = String::new();
+        let _ = stream.read_to_string(&mut out);
+        out
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn slow_clients_get_408_or_are_dropped() {
+        let connections = ConnSettings {
+            idle_timeout: Duration::from_millis(250),
+            header_timeout: Duration::from_millis(500),
+            body_timeout: Duration::from_millis(150),
+            ..ConnSettings::default()
+        };
+        let (addr, state, _rx) = spawn_loop(crate::config::Config { connections, ..Default::default() });
+
+        // Callado: se corta sin respuesta
+        let silent = TcpStream::connect(addr).unwrap();
+        // Slowloris: un byte cada 50 ms, nunca termina las cabeceras
+        let trickle = TcpStream::connect(addr).unwrap();
+        let mut writer = trickle.try_clone().unwrap();
+        std::thread::spawn(move || {
+            writer.write_all(b"GET / HTTP/1.0\r\n").unwrap();
+            while writer.write_all(b"X").is_ok() {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+        // Cuerpo incompleto
+        let mut body = TcpStream::connect(addr).unwrap();
+        body.write_all(b"POST /reverse HTTP/1.0\r\nContent-Length: 10\r\n\r\nab").unwrap();
+
+        assert_eq!(read_reply(silent), "");
+        assert!(read_reply(trickle).starts_with("HTTP/1.0 408"));
+        assert!(read_reply(body).starts_with("HTTP/1.0 408"));
+        let v = state.lock().unwrap().connection_violations;
+        assert_eq!((v.idle_timeout, v.header_timeout, v.body_timeout), (1, 1, 1));
+        // El lugar se libera apenas después de cerrar el socket
+        let start = Instant::now();
+        while state.lock().unwrap().connections.count() != 0 {
+            assert!(start.elapsed() < Duration::from_secs(2), "quedaron lugares sin liberar");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn connections_over_the_per_ip_cap_are_dropped() {
+        let connections = ConnSettings { max_per_ip: 2, ..ConnSettings::default() };
+        let (addr, state, rx) = spawn_loop(crate::config::Config { connections, ..Default::default() });
+
+        let _held: Vec<TcpStream> = (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        let mut extra = TcpStream::connect(addr).unwrap();
+        let _ = extra.write_all(b"GET /status HTTP/1.0\r\n\r\n");
+        assert_eq!(read_reply(extra), "");
+        assert!(rx.try_recv().is_err());
+        assert_eq!(state.lock().unwrap().connection_violations.max_per_ip, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn idle_connections_do_not_block_others() {
+        let (addr, state, rx) = spawn_loop(crate::config::Config::default());
+
+        // Clientes que no mandan nada o se quedan a mitad de la petición
+        let mut idle: Vec<TcpStream> = (0..200).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        idle[0].write_all(b"GET /slow HTTP/1.0\r\nHost: x").unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET /status HTTP/1.0\r\n\r\n").unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), "/status");
+
+        idle[0].write_all(b"\r\n\r\n").unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), "/slow");
+        let start = std::time::Instant::now();
+        while state.lock().unwrap().pending_connections != 199 {
+            assert!(start.elapsed() < Duration::from_secs(2), "no se publicaron las conexiones en espera");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn half_closed_requests_are_dispatched_or_rejected() {
+        let (addr, _state, rx) = spawn_loop(crate::config::Config::default());
+
+        // Completa y cerrando la escritura: se despacha como cualquier otra
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET /status HTTP/1.0\r\n\r\n").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), "/status");
+
+        // Sin la línea vacía final: no llegará más, se responde 400
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET /help HTTP/1.0\r\n").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        assert!(read_reply(client).starts_with("HTTP/1.0 400"));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -1,23 +1,75 @@
+use crate::registry::{self, ParamKind, ParamSpec, Registry, Step};
+use crate::cputime::{cpu_since, thread_cpu_time};
+use crate::cache::CacheReport;
+use crate::executor::Executor;
+use crate::frontend::ConnViolations;
+use crate::http::{Cancel, Request};
+use crate::random;
+use crate::pool::{PoolStatus, ScalingEvent};
+use crate::scheduler::{PolicyReport, PolicyStats, SchedulerInfo};
 use crate::state::SharedState;
+use crate::stats::LatencySummary;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use chrono::Utc;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
 use std::fs::OpenOptions;
 use std::io::Write;
-use rand::Rng;
-use serde_json;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use sha2::{Sha256, Digest};
-use hex;
 use std::time::{Duration, Instant};
 use std::thread::sleep;
 use std::thread;
+use std::sync::{Arc, Mutex};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Respuesta JSON de /status, incluyendo PID y lista de workers
 #[derive(Serialize)]
-struct StatusResponse {
+struct StatusResponse<'a> {
     uptime_seconds: i64,
     total_connections: usize,
+    rate_limited: usize,
+    coalesced: CoalescedResponse<'a>,
+    cache: CacheReport,
+    client_abandoned: usize,
+    pending_connections: usize,
+    open_connections: usize,
+    connection_violations: ConnViolations,
     pid: u32,
     workers: Vec<WorkerInfoResponse>,
+    scheduler: SchedulerResponse,
+    pools: Vec<PoolStatus>,
+    scaling: ScalingResponse<'a>,
+}
+
+/// Peticiones que compartieron un cálculo en curso, en total y por ruta
+#[derive(Serialize)]
+struct CoalescedResponse<'a> {
+    enabled: bool,
+    total: usize,
+    routes: &'a BTreeMap<String, usize>,
+}
+
+/// Presupuesto de hilos y últimos eventos del auto-escalador
+#[derive(Serialize)]
+struct ScalingResponse<'a> {
+    thread_budget: usize,
+    threads: usize,
+    events: &'a VecDeque<ScalingEvent>,
+}
+
+/// Política activa y estadísticas por política, sumadas entre todos los pools
+#[derive(Serialize)]
+struct SchedulerResponse {
+    policy: &'static str,
+    quantum_ms: f64,
+    aging_per_sec: f64,
+    queued: usize,
+    policies: BTreeMap<&'static str, PolicyReport>,
 }
 
 #[derive(Serialize)]
@@ -25,6 +77,7 @@ struct WorkerInfoResponse {
     command: String,
     thread_id: String,
     busy: bool,
+    cpu_time_ms: f64,
 }
 
 pub fn handle_status(state: SharedState) -> String {
@@ -36,13 +89,50 @@ pub fn handle_status(state: SharedState) -> String {
         command: w.command.clone(),
         thread_id: w.thread_id.clone(),
         busy: w.busy,
+        cpu_time_ms: w.cpu_time_ms,
     }).collect();
 
+    let mut merged: BTreeMap<&'static str, PolicyStats> = BTreeMap::new();
+    for pool in &st.pools {
+        for (name, stats) in pool.scheduler.stats() {
+            merged.entry(name).or_default().merge(&stats);
+        }
+    }
+    let settings = st.config.scheduler_settings;
+    let scheduler = SchedulerResponse {
+        policy: st.pools.first().map_or(st.config.scheduler, |p| p.scheduler.policy()).name(),
+        quantum_ms: settings.quantum.as_secs_f64() * 1000.0,
+        aging_per_sec: settings.aging_per_sec,
+        queued: st.pools.iter().map(|p| p.scheduler.queued()).sum(),
+        policies: merged.iter().map(|(name, stats)| (*name, stats.report())).collect(),
+    };
+
+    let pools: Vec<PoolStatus> = st.pools.iter().map(|p| p.status()).collect();
+    let scaling = ScalingResponse {
+        thread_budget: st.config.scaling.thread_budget,
+        threads: pools.iter().map(|p| p.size).sum(),
+        events: &st.scaling_events,
+    };
+
     let resp = StatusResponse {
         uptime_seconds: uptime.num_seconds(),
         total_connections: st.total_connections,
+        rate_limited: st.rate_limited,
+        coalesced: CoalescedResponse {
+            enabled: st.config.coalesce,
+            total: st.coalesced.values().sum(),
+            routes: &st.coalesced,
+        },
+        cache: st.cache.report(),
+        client_abandoned: st.client_abandoned,
+        pending_connections: st.pending_connections,
+        open_connections: st.connections.count(),
+        connection_violations: st.connection_violations,
         pid: st.pid,
         workers: workers_resp,
+        scheduler,
+        pools,
+        scaling,
     };
     let body = serde_json::to_string(&resp).unwrap();
 
@@ -63,18 +153,23 @@ fn fib(n: usize) -> usize {
     }
 }
 
+/// Parámetros de /fibonacci
+pub const FIB_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("num", ParamKind::UInt, "posición en la serie"),
+];
+
 /// Parsea y valida el parámetro num=N para /fibonacci
 ///   - Err si falta `num`
 ///   - Err si `num` no es un entero >= 0
 pub fn parse_fib_param(query: &str) -> Result<usize, String> {
-    query
-        .split('&')
-        .find_map(|p| p.strip_prefix("num="))
-        .ok_or_else(|| "Parámetro 'num' requerido".to_string())
-        .and_then(|v| {
-            v.parse::<usize>()
-             .map_err(|_| "Parámetro 'num' debe ser un entero no negativo".to_string())
-        })
+    let params = registry::validate(FIB_PARAMS, query)?;
+    Ok(params.usize("num").unwrap_or_default())
+}
+
+/// Costo estimado de /fibonacci: el recursivo crece como phi^n (~5 ms con n = 30)
+pub fn fibonacci_cost(params: &registry::Params) -> f64 {
+    let n = params.u64("num").unwrap_or_default() as f64;
+    5.0 * 1.618f64.powf(n - 30.0)
 }
 
 /// Handler de Fibonacci recibe un 'n' validado
@@ -88,35 +183,24 @@ pub fn handle_fibonacci(n: usize) -> String {
     )
 }
 
+/// Parámetros de /createfile
+pub const CREATEFILE_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("name", ParamKind::Text, "nombre del archivo"),
+    ParamSpec::required("content", ParamKind::Text, "contenido a escribir"),
+    ParamSpec::optional("repeat", ParamKind::UInt, "1", "veces que se repite el contenido")
+        .range(Some(1), None),
+];
+
 /// Parsea y valida parámetros para /createfile
 ///   - `name` y `content` son obligatorios
 ///   - `repeat` es opcional (por defecto 1) y debe ser entero > 0
 pub fn parse_createfile_params(query: &str) -> Result<(String, String, usize), String> {
-    let mut params = query
-        .split('&')
-        .filter_map(|p| {
-            let mut kv = p.splitn(2, '=');
-            match (kv.next(), kv.next()) {
-                (Some(k), Some(v)) => Some((k.to_string(), v.to_string())),
-                _ => None,
-            }
-        })
-        .collect::<std::collections::HashMap<_, _>>();
-
-    let name = params
-        .remove("name")
-        .ok_or_else(|| "Parámetro 'name' requerido".to_string())?;
-    let content = params
-        .remove("content")
-        .ok_or_else(|| "Parámetro 'content' requerido".to_string())?;
-    let repeat = params
-        .remove("repeat")
-        .map_or(Ok(1), |v| {
-            v.parse::<usize>()
-             .map_err(|_| "Parámetro 'repeat' debe ser un entero positivo".to_string())
-        })?;
-
-    Ok((name, content, repeat))
+    let params = registry::validate(CREATEFILE_PARAMS, query)?;
+    Ok((
+        params.str("name").unwrap_or_default().to_string(),
+        params.str("content").unwrap_or_default().to_string(),
+        params.usize("repeat").unwrap_or(1),
+    ))
 }
 
 /// Handler de createfile, recibe parámetros ya validados
@@ -148,13 +232,15 @@ pub fn handle_createfile(name: &str, content: &str, repeat: usize) -> String {
     }
 }
 
+/// Parámetros de /deletefile
+pub const DELETEFILE_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("name", ParamKind::Text, "nombre del archivo"),
+];
+
 /// Parsea y valida `name` para /deletefile
 pub fn parse_deletefile_param(query: &str) -> Result<String, String> {
-    query
-        .split('&')
-        .find_map(|p| p.strip_prefix("name="))
-        .map(|s| s.to_string())
-        .ok_or_else(|| "Parámetro 'name' requerido".to_string())
+    let params = registry::validate(DELETEFILE_PARAMS, query)?;
+    Ok(params.str("name").unwrap_or_default().to_string())
 }
 
 /// Handler de deletefile, recibe `name` ya validado
@@ -171,38 +257,253 @@ pub fn handle_deletefile(name: &str) -> String {
     }
 }
 
+/// Parámetros de las rutas de texto (/reverse, /toupper, /hash, ...). El
+/// texto también puede llegar en el cuerpo de un POST
+pub const TEXT_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional_no_default("text", ParamKind::Text, "texto de entrada (URL-encoded; o en el cuerpo con POST)"),
+];
+
+/// Decodifica un valor de la query (`%XX` y `+` como espacio)
+fn query_text(raw: &str) -> Result<String, String> {
+    percent_decode_str(&raw.replace('+', " "))
+        .decode_utf8()
+        .map(|t| t.into_owned())
+        .map_err(|_| "Parámetro 'text' no es UTF-8 válido".to_string())
+}
+
 /// Parsea y valida `text` para /reverse
 ///   - Error 400 si falta `text`
 pub fn parse_text_param(query: &str) -> Result<String, String> {
-    query
-        .split('&')
-        .find_map(|p| p.strip_prefix("text="))
-        .map(|s| s.to_string())
-        .ok_or_else(|| "Parámetro 'text' requerido".to_string())
+    let params = registry::validate(TEXT_PARAMS, query)?;
+    query_text(params.str("text").ok_or("Parámetro 'text' requerido")?)
+}
+
+/// Texto de entrada de una petición: `text` de la query o, si no está, el cuerpo
+///   - Err si no hay ninguno de los dos o no es UTF-8 válido
+pub fn text_input(req: &Request) -> Result<String, String> {
+    match req.params.str("text") {
+        Some(raw) => query_text(raw),
+        None => body_text(req),
+    }
+}
+
+/// Como `text_input`, pero `text` llega tal cual, sin decodificar: /hash
+/// conserva así sus resultados de siempre (`a+b` no se hashea como `a b`)
+pub fn raw_text_input(req: &Request) -> Result<String, String> {
+    match req.params.str("text") {
+        Some(raw) => Ok(raw.to_string()),
+        None => body_text(req),
+    }
+}
+
+fn body_text(req: &Request) -> Result<String, String> {
+    if req.body.is_empty() {
+        return Err("Parámetro 'text' requerido".to_string());
+    }
+    String::from_utf8(req.body.clone()).map_err(|_| "El cuerpo no es UTF-8 válido".to_string())
+}
+
+/// Operaciones de texto; cada una es una ruta `/{nombre}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextOp {
+    Reverse,
+    ToUpper,
+    ToLower,
+    TitleCase,
+    WordCount,
+    CharCount,
+    Palindrome,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+}
+
+impl TextOp {
+    pub const ALL: [TextOp; 11] = [
+        TextOp::Reverse,
+        TextOp::ToUpper,
+        TextOp::ToLower,
+        TextOp::TitleCase,
+        TextOp::WordCount,
+        TextOp::CharCount,
+        TextOp::Palindrome,
+        TextOp::Base64Encode,
+        TextOp::Base64Decode,
+        TextOp::UrlEncode,
+        TextOp::UrlDecode,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            TextOp::Reverse => "reverse",
+            TextOp::ToUpper => "toupper",
+            TextOp::ToLower => "tolower",
+            TextOp::TitleCase => "titlecase",
+            TextOp::WordCount => "wordcount",
+            TextOp::CharCount => "charcount",
+            TextOp::Palindrome => "palindrome",
+            TextOp::Base64Encode => "base64encode",
+            TextOp::Base64Decode => "base64decode",
+            TextOp::UrlEncode => "urlencode",
+            TextOp::UrlDecode => "urldecode",
+        }
+    }
+
+    pub const fn path(self) -> &'static str {
+        match self {
+            TextOp::Reverse => "/reverse",
+            TextOp::ToUpper => "/toupper",
+            TextOp::ToLower => "/tolower",
+            TextOp::TitleCase => "/titlecase",
+            TextOp::WordCount => "/wordcount",
+            TextOp::CharCount => "/charcount",
+            TextOp::Palindrome => "/palindrome",
+            TextOp::Base64Encode => "/base64encode",
+            TextOp::Base64Decode => "/base64decode",
+            TextOp::UrlEncode => "/urlencode",
+            TextOp::UrlDecode => "/urldecode",
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            TextOp::Reverse => "invierte el texto (respeta grafemas: emoji, acentos combinados)",
+            TextOp::ToUpper => "convierte texto a MAYÚSCULAS",
+            TextOp::ToLower => "convierte texto a minúsculas",
+            TextOp::TitleCase => "pone en mayúscula la primera letra de cada palabra",
+            TextOp::WordCount => "cuenta palabras y líneas",
+            TextOp::CharCount => "cuenta grafemas, caracteres y bytes",
+            TextOp::Palindrome => "indica si es palíndromo (ignora mayúsculas, acentos, espacios y signos)",
+            TextOp::Base64Encode => "codifica el texto en base64",
+            TextOp::Base64Decode => "decodifica base64 a texto UTF-8",
+            TextOp::UrlEncode => "codifica el texto para usarlo en una URL",
+            TextOp::UrlDecode => "decodifica %XX de una URL",
+        }
+    }
+}
+
+/// Medidas del texto de entrada que acompañan a cada respuesta
+#[derive(Serialize)]
+struct TextStats {
+    bytes: usize,
+    chars: usize,
+    graphemes: usize,
+    words: usize,
+    lines: usize,
+}
+
+impl TextStats {
+    fn of(text: &str) -> Self {
+        TextStats {
+            bytes: text.len(),
+            chars: text.chars().count(),
+            graphemes: text.graphemes(true).count(),
+            words: text.unicode_words().count(),
+            lines: text.lines().count(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TextResponse {
+    op: &'static str,
+    result: serde_json::Value,
+    input: TextStats,
+}
+
+/// Palabras con la primera letra en mayúscula y el resto en minúscula
+fn title_case(text: &str) -> String {
+    text.split_word_bounds()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Compara solo letras y dígitos, sin mayúsculas ni acentos
+fn is_palindrome(text: &str) -> bool {
+    let letters: Vec<char> = text
+        .nfd()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    letters.iter().eq(letters.iter().rev())
+}
+
+/// Aplica la operación: Err con el mensaje para un 400 si la entrada no sirve
+pub fn apply_text_op(op: TextOp, text: &str) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+    Ok(match op {
+        TextOp::Reverse => Value::from(text.graphemes(true).rev().collect::<String>()),
+        TextOp::ToUpper => Value::from(text.to_uppercase()),
+        TextOp::ToLower => Value::from(text.to_lowercase()),
+        TextOp::TitleCase => Value::from(title_case(text)),
+        TextOp::WordCount => Value::from(text.unicode_words().count()),
+        TextOp::CharCount => Value::from(text.graphemes(true).count()),
+        TextOp::Palindrome => Value::from(is_palindrome(text)),
+        TextOp::Base64Encode => Value::from(BASE64.encode(text)),
+        TextOp::Base64Decode => {
+            let bytes = BASE64
+                .decode(text.trim())
+                .map_err(|_| "El texto no es base64 válido".to_string())?;
+            let decoded = String::from_utf8(bytes).map_err(|_| "El base64 no contiene texto UTF-8".to_string())?;
+            Value::from(decoded)
+        }
+        TextOp::UrlEncode => Value::from(utf8_percent_encode(text, NON_ALPHANUMERIC).to_string()),
+        TextOp::UrlDecode => {
+            let decoded = percent_decode_str(text)
+                .decode_utf8()
+                .map_err(|_| "El texto decodificado no es UTF-8 válido".to_string())?;
+            Value::from(decoded.into_owned())
+        }
+    })
+}
+
+/// Handler de las rutas de texto: JSON con el resultado y medidas de la entrada
+pub fn handle_text(op: TextOp, text: &str) -> String {
+    match apply_text_op(op, text) {
+        Ok(result) => json_ok(&TextResponse { op: op.name(), result, input: TextStats::of(text) }),
+        Err(msg) => format!("HTTP/1.0 400 Bad Request\r\n\r\n{}\n", msg),
+    }
 }
 
 /// Handler de reverse, recibe `text` ya validado
 pub fn handle_reverse(text: &str) -> String {
-    let reversed: String = text.chars().rev().collect();
-    format!(
-        "HTTP/1.0 200 OK\r\n\
-         Content-Type: text/plain\r\n\r\n\
-         {}\n",
-        reversed
-    )
+    handle_text(TextOp::Reverse, text)
 }
 
 /// Handler de toupper, recibe `text` ya validado
 pub fn handle_toupper(text: &str) -> String {
-    let upper = text.to_uppercase();
-    format!(
-        "HTTP/1.0 200 OK\r\n\
-         Content-Type: text/plain\r\n\r\n\
-         {}\n",
-        upper
-    )
+    handle_text(TextOp::ToUpper, text)
 }
 
+/// Parámetros de /random
+pub const RANDOM_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("count", ParamKind::UInt, "cantidad de valores").range(Some(1), Some(random::MAX_COUNT)),
+    ParamSpec::optional_no_default("min", ParamKind::Int, "límite inferior (inclusivo, dist=uniform)"),
+    ParamSpec::optional_no_default("max", ParamKind::Int, "límite superior (inclusivo, dist=uniform)"),
+    ParamSpec::optional_no_default("seed", ParamKind::UInt, "semilla para repetir la secuencia"),
+    ParamSpec::optional("type", ParamKind::Text, "int", "qué generar")
+        .one_of(&["int", "float", "string", "uuid", "choice", "shuffle"]),
+    ParamSpec::optional("dist", ParamKind::Text, "uniform", "distribución de int y float")
+        .one_of(&["uniform", "normal", "exponential", "poisson"]),
+    ParamSpec::optional("mean", ParamKind::Float, "0", "media (dist=normal)"),
+    ParamSpec::optional("stddev", ParamKind::Float, "1", "desviación estándar (dist=normal)").range(Some(0), None),
+    ParamSpec::optional("lambda", ParamKind::Float, "1", "tasa (dist=exponential o poisson)"),
+    ParamSpec::optional("unique", ParamKind::Text, "false", "sin repetición (int uniforme o choice)")
+        .one_of(&["true", "false"]),
+    ParamSpec::optional_no_default("items", ParamKind::Text, "lista separada por comas (type=choice o shuffle)"),
+    ParamSpec::optional("length", ParamKind::UInt, "16", "largo de cada cadena (type=string)").range(Some(1), Some(4096)),
+    ParamSpec::optional("charset", ParamKind::Text, "alnum", "caracteres de las cadenas (type=string)")
+        .one_of(&["alnum", "alpha", "hex", "digits"]),
+    ParamSpec::optional("format", ParamKind::Text, "json", "formato de salida").one_of(&["json", "csv", "lines"]),
+];
+
 /// Parsea y valida parámetros para /random
 ///   - `count` es obligatorio y debe ser entero > 0
 ///   - `min` es obligatorio y debe ser entero
@@ -210,39 +511,14 @@ pub fn handle_toupper(text: &str) -> String {
 ///   - Error 400 si falta alguno o no cumple el formato
 ///   - Error 400 si `min` > `max`
 pub fn parse_random_params(query: &str) -> Result<(usize, i64, i64), String> {
-    let mut params = query
-        .split('&')
-        .filter_map(|p| {
-            let mut kv = p.splitn(2, '=');
-            match (kv.next(), kv.next()) {
-                (Some(k), Some(v)) => Some((k, v)),
-                _ => None,
-            }
-        })
-        .collect::<std::collections::HashMap<_, _>>();
-
-    let count = params
-        .remove("count")
-        .ok_or_else(|| "Parámetro 'count' requerido".to_string())
-        .and_then(|v| v.parse::<usize>()
-            .map_err(|_| "Parámetro 'count' debe ser un entero positivo".to_string()))
-        .and_then(|c| if c > 0 {
-            Ok(c)
-        } else {
-            Err("Parámetro 'count' debe ser mayor que cero".to_string())
-        })?;
-
-    let min = params
-        .remove("min")
-        .ok_or_else(|| "Parámetro 'min' requerido".to_string())
-        .and_then(|v| v.parse::<i64>()
-            .map_err(|_| "Parámetro 'min' debe ser un entero".to_string()))?;
+    random_args(&registry::validate(RANDOM_PARAMS, query)?)
+}
 
-    let max = params
-        .remove("max")
-        .ok_or_else(|| "Parámetro 'max' requerido".to_string())
-        .and_then(|v| v.parse::<i64>()
-            .map_err(|_| "Parámetro 'max' debe ser un entero".to_string()))?;
+/// Extrae los argumentos de /random uniforme y comprueba que `min` <= `max`
+pub fn random_args(params: &registry::Params) -> Result<(usize, i64, i64), String> {
+    let count = params.usize("count").unwrap_or(1);
+    let min = params.i64("min").ok_or("Parámetro 'min' requerido")?;
+    let max = params.i64("max").ok_or("Parámetro 'max' requerido")?;
 
     if min > max {
         return Err("Parámetro 'min' no puede ser mayor que 'max'".to_string());
@@ -251,20 +527,10 @@ pub fn parse_random_params(query: &str) -> Result<(usize, i64, i64), String> {
     Ok((count, min, max))
 }
 
-/// Handler de /random recibe valores ya validados
-pub fn handle_random(count: usize, min: i64, max: i64) -> String {
-    let mut rng = rand::thread_rng();
-    let nums: Vec<i64> = (0..count)
-        .map(|_| rng.gen_range(min..=max))
-        .collect();
-
-    let body = serde_json::to_string(&nums).unwrap_or_else(|_| "[]".to_string());
-    format!(
-        "HTTP/1.0 200 OK\r\n\
-         Content-Type: application/json\r\n\r\n\
-         {}\n",
-        body
-    )
+/// Handler de /random recibe valores ya validados. La misma semilla da la
+/// misma secuencia; la semilla usada se informa en la cabecera `X-Random-Seed`
+pub fn handle_random(count: usize, min: i64, max: i64, seed: u64) -> String {
+    random::respond(&random::RandomSpec::ints(count, min, max), seed, None)
 }
 
 /// Devuelve la hora actual del sistema en formato ISO-8601 (UTC)
@@ -294,172 +560,879 @@ pub fn handle_hash(text: &str) -> String {
     )
 }
 
-/// Parsea y valida parámetros para /simulate
-///   - `seconds` es obligatorio y debe ser entero >= 0
-///   - `task` es obligatorio
-pub fn parse_simulate_params(query: &str) -> Result<(u64, String), String> {
-    let mut params = query
-        .split('&')
-        .filter_map(|p| {
-            let mut kv = p.splitn(2, '=');
-            match (kv.next(), kv.next()) {
-                (Some(k), Some(v)) => Some((k, v)),
-                _ => None,
+/// Parámetros comunes de duración para /sleep y /simulate. Se usa el
+/// primero presente entre `duration`, `ms` y `seconds`
+const DELAY_PARAMS: [ParamSpec; 4] = [
+    ParamSpec::optional_no_default("seconds", ParamKind::UInt, "segundos enteros"),
+    ParamSpec::optional_no_default("ms", ParamKind::UInt, "milisegundos"),
+    ParamSpec::optional_no_default("duration", ParamKind::Duration, "duración con unidad: 250ms, 1.5s, 2m"),
+    ParamSpec::optional_no_default("jitter", ParamKind::Duration, "variación aleatoria de ± jitter"),
+];
+
+/// Duración pedida a /sleep o /simulate y su jitter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delay {
+    pub requested: Duration,
+    pub jitter: Duration,
+}
+
+impl Delay {
+    pub fn fixed(requested: Duration) -> Self {
+        Delay { requested, jitter: Duration::ZERO }
+    }
+
+    /// Duración a esperar: `requested` ± un valor uniforme en `[0, jitter]`
+    pub fn target(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.requested;
+        }
+        let j = self.jitter.as_secs_f64();
+        let offset = rand::thread_rng().gen_range(-j..=j);
+        Duration::from_secs_f64((self.requested.as_secs_f64() + offset).max(0.0))
+    }
+}
+
+/// Extrae la duración de parámetros ya validados
+///   - Err si no viene ninguno de `duration`, `ms` o `seconds`
+pub fn delay_args(params: &registry::Params) -> Result<Delay, String> {
+    let requested = params
+        .duration("duration")
+        .or_else(|| params.u64("ms").map(Duration::from_millis))
+        .or_else(|| params.u64("seconds").map(Duration::from_secs))
+        .ok_or_else(|| "Parámetro 'seconds' requerido".to_string())?;
+    Ok(Delay {
+        requested,
+        jitter: params.duration("jitter").unwrap_or(Duration::ZERO),
+    })
+}
+
+/// Resultado JSON de una espera: lo pedido y lo que realmente se midió
+#[derive(Serialize)]
+struct DelayReport {
+    message: String,
+    requested_ms: f64,
+    jitter_ms: f64,
+    target_ms: f64,
+    measured_ms: f64,
+}
+
+/// Cómo ocupa /simulate el tiempo pedido
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulateMode {
+    /// Duerme el hilo
+    Sleep,
+    /// Encadena hashes SHA-256 sin soltar la CPU
+    Cpu,
+    /// Mitad del tiempo CPU, mitad sleep
+    Mixed,
+}
+
+/// Cada cuánto una espera larga mira si el cliente la abandonó
+const CANCEL_CHECK: Duration = Duration::from_millis(10);
+
+/// Quema CPU encadenando SHA-256 hasta agotar `dur`; devuelve las iteraciones hechas
+fn burn_cpu(dur: Duration) -> u64 {
+    let start = Instant::now();
+    let mut digest = [0u8; 32];
+    let mut iterations = 0u64;
+    while start.elapsed() < dur {
+        // Se revisa el reloj cada 256 hashes para no medir sólo Instant::now
+        for _ in 0..256 {
+            digest = Sha256::digest(digest).into();
+        }
+        iterations += 256;
+    }
+    std::hint::black_box(digest);
+    iterations
+}
+
+/// Avance de una espera de /sleep o /simulate. Puede completarse de una vez
+/// o por porciones, cuando el scheduler reparte el hilo en round-robin
+pub struct DelayWork {
+    target: Duration,
+    done: Duration,
+    started: Instant,
+    iterations: u64,
+    cpu: Duration,
+}
+
+impl DelayWork {
+    fn new(delay: &Delay) -> Self {
+        DelayWork {
+            target: delay.target(),
+            done: Duration::ZERO,
+            started: Instant::now(),
+            iterations: 0,
+            cpu: Duration::ZERO,
+        }
+    }
+
+    /// Ocupa hasta `slice` más según `mode`; true si ya se cumplió el objetivo.
+    /// En `Mixed` la primera mitad del objetivo es CPU y la segunda sleep.
+    /// Avanza de a `CANCEL_CHECK` para cortar pronto si el cliente se fue.
+    /// `done` sale del reloj, no de sumar trozos: lo que se pasa cada
+    /// `sleep` no se acumula
+    fn advance(&mut self, mode: SimulateMode, slice: Duration, cancel: &Cancel) -> bool {
+        let base = self.done;
+        let start = Instant::now();
+        let deadline = start + slice.min(self.target.saturating_sub(base));
+        let cpu_start = thread_cpu_time();
+        loop {
+            let now = Instant::now();
+            self.done = base + (now - start);
+            if now >= deadline || cancel.is_cancelled() {
+                break;
             }
-        })
-        .collect::<std::collections::HashMap<_, _>>();
+            let chunk = CANCEL_CHECK.min(deadline - now);
+            let cpu_part = match mode {
+                SimulateMode::Sleep => Duration::ZERO,
+                SimulateMode::Cpu => chunk,
+                SimulateMode::Mixed => (self.target / 2).saturating_sub(self.done).min(chunk),
+            };
+            if !cpu_part.is_zero() {
+                self.iterations += burn_cpu(cpu_part);
+            }
+            sleep((now + chunk).saturating_duration_since(Instant::now()));
+        }
+        self.cpu += cpu_since(cpu_start);
+        self.done >= self.target
+    }
 
-    let seconds = params
-        .remove("seconds")
-        .ok_or_else(|| "Parámetro 'seconds' requerido".to_string())
-        .and_then(|v| v.parse::<u64>()
-            .map_err(|_| "Parámetro 'seconds' debe ser un entero no negativo".to_string()))?;
+    fn report(&self, delay: &Delay, message: String) -> DelayReport {
+        DelayReport {
+            message,
+            requested_ms: delay.requested.as_secs_f64() * 1000.0,
+            jitter_ms: delay.jitter.as_secs_f64() * 1000.0,
+            target_ms: self.target.as_secs_f64() * 1000.0,
+            measured_ms: self.started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
 
-    let task = params
-        .remove("task")
-        .ok_or_else(|| "Parámetro 'task' requerido".to_string())?;
+/// Avanza la espera guardada en `req.resume` (la crea en la primera porción)
+/// y arma la respuesta con `finish` cuando termina
+fn delay_step(
+    req: &mut Request,
+    delay: &Delay,
+    mode: SimulateMode,
+    slice: Duration,
+    finish: impl FnOnce(&DelayWork) -> String,
+) -> Step {
+    let resume = req
+        .resume
+        .get_or_insert_with(|| Arc::new(Mutex::new(DelayWork::new(delay))))
+        .clone();
+    let mut guard = resume.lock().unwrap();
+    let Some(work) = guard.downcast_mut::<DelayWork>() else {
+        return Step::Done("HTTP/1.0 500 Internal Server Error\r\n\r\nEstado de espera inválido\n".to_string());
+    };
+    let done = work.advance(mode, slice, &req.cancel);
+    if req.cancel.is_cancelled() {
+        // Nadie espera la respuesta: se cierra sin escribir
+        Step::Done(String::new())
+    } else if done {
+        Step::Done(finish(work))
+    } else {
+        Step::Yield
+    }
+}
 
-    Ok((seconds, task.to_string()))
+/// Costo estimado de /sleep y /simulate: la duración pedida en ms
+pub fn delay_cost(params: &registry::Params) -> f64 {
+    delay_args(params).map_or(1.0, |d| d.requested.as_secs_f64() * 1000.0)
 }
 
-/// Handler de /simulate, recibe valores ya validados
-pub fn handle_simulate(seconds: u64, task_name: String) -> String {
-    sleep(Duration::from_secs(seconds));
+fn json_ok<T: Serialize>(value: &T) -> String {
     format!(
         "HTTP/1.0 200 OK\r\n\
-         Content-Type: text/plain\r\n\r\n\
-         Tarea '{}' completada en {} segundo(s)\n",
-        task_name, seconds
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        serde_json::to_string(value).unwrap()
     )
 }
 
-/// Parsea y valida `seconds` para /sleep
-///   - Error 400 si falta `seconds`
-///   - Error 400 si no es un entero no negativo
-pub fn parse_sleep_param(query: &str) -> Result<u64, String> {
-    query
-        .split('&')
-        .find_map(|p| p.strip_prefix("seconds="))
-        .ok_or_else(|| "Parámetro 'seconds' requerido".to_string())
-        .and_then(|v| v.parse::<u64>()
-            .map_err(|_| "Parámetro 'seconds' debe ser un entero no negativo".to_string()))
+/// Parámetros de /simulate
+pub const SIMULATE_PARAMS: &[ParamSpec] = &[
+    DELAY_PARAMS[0],
+    DELAY_PARAMS[1],
+    DELAY_PARAMS[2],
+    DELAY_PARAMS[3],
+    ParamSpec::required("task", ParamKind::Text, "nombre de la tarea"),
+    ParamSpec::optional("mode", ParamKind::Text, "sleep", "sleep duerme, cpu hace hashing, mixed reparte el tiempo")
+        .one_of(&["sleep", "cpu", "mixed"]),
+];
+
+/// Modo de /simulate a partir de parámetros ya validados
+pub fn simulate_mode(params: &registry::Params) -> SimulateMode {
+    match params.str("mode") {
+        Some("cpu") => SimulateMode::Cpu,
+        Some("mixed") => SimulateMode::Mixed,
+        _ => SimulateMode::Sleep,
+    }
 }
 
-/// Handler de /sleep, recibe `seconds` ya validado
-pub fn handle_sleep(seconds: u64) -> String {
-    std::thread::sleep(std::time::Duration::from_secs(seconds));
-    format!(
-        "HTTP/1.0 200 OK\r\n\
-         Content-Type: text/plain\r\n\r\n\
-         Espera de {} segundo(s) completada\n",
-        seconds
-    )
+/// Parsea y valida parámetros para /simulate
+///   - `seconds`, `ms` o `duration` es obligatorio
+///   - `task` es obligatorio
+pub fn parse_simulate_params(query: &str) -> Result<(Delay, String), String> {
+    let params = registry::validate(SIMULATE_PARAMS, query)?;
+    let delay = delay_args(&params)?;
+    Ok((delay, params.str("task").unwrap_or_default().to_string()))
+}
+
+#[derive(Serialize)]
+struct SimulateReport {
+    task: String,
+    mode: SimulateMode,
+    #[serde(flatten)]
+    delay: DelayReport,
+    /// Hashes SHA-256 calculados (0 en modo sleep)
+    iterations: u64,
+    /// CPU consumida por el hilo durante la tarea
+    cpu_ms: f64,
+}
+
+fn simulate_response(delay: &Delay, task_name: String, mode: SimulateMode, work: &DelayWork) -> String {
+    let message = format!(
+        "Tarea '{}' completada en {} segundo(s)",
+        task_name,
+        delay.requested.as_secs_f64()
+    );
+    json_ok(&SimulateReport {
+        task: task_name,
+        mode,
+        delay: work.report(delay, message),
+        iterations: work.iterations,
+        cpu_ms: work.cpu.as_secs_f64() * 1000.0,
+    })
+}
+
+/// Handler de /simulate, recibe valores ya validados
+pub fn handle_simulate(delay: &Delay, task_name: String, mode: SimulateMode) -> String {
+    let mut work = DelayWork::new(delay);
+    work.advance(mode, Duration::MAX, &Cancel::default());
+    simulate_response(delay, task_name, mode, &work)
+}
+
+/// /simulate por porciones de a lo sumo `slice` (handler cooperativo)
+pub fn simulate_step(req: &mut Request, delay: &Delay, task_name: String, mode: SimulateMode, slice: Duration) -> Step {
+    delay_step(req, delay, mode, slice, |work| simulate_response(delay, task_name, mode, work))
+}
+
+/// Parámetros de /sleep
+pub const SLEEP_PARAMS: &[ParamSpec] = &DELAY_PARAMS;
+
+/// Parsea y valida la duración para /sleep
+///   - Error 400 si falta `seconds` (o `ms` / `duration`)
+///   - Error 400 si no tiene el formato esperado
+pub fn parse_sleep_param(query: &str) -> Result<Delay, String> {
+    delay_args(&registry::validate(SLEEP_PARAMS, query)?)
+}
+
+fn sleep_response(delay: &Delay, work: &DelayWork) -> String {
+    let message = format!("Espera de {} segundo(s) completada", delay.requested.as_secs_f64());
+    json_ok(&work.report(delay, message))
+}
+
+/// Handler de /sleep, recibe la duración ya validada
+pub fn handle_sleep(delay: &Delay) -> String {
+    let mut work = DelayWork::new(delay);
+    work.advance(SimulateMode::Sleep, Duration::MAX, &Cancel::default());
+    sleep_response(delay, &work)
+}
+
+/// /sleep por porciones de a lo sumo `slice` (handler cooperativo)
+pub fn sleep_step(req: &mut Request, delay: &Delay, slice: Duration) -> Step {
+    delay_step(req, delay, SimulateMode::Sleep, slice, |work| sleep_response(delay, work))
+}
+
+/// Parámetros de /loadtest
+pub const LOADTEST_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("tasks", ParamKind::UInt, "número total de tareas (máximo configurable)")
+        .range(Some(1), None),
+    ParamSpec::optional("sleep", ParamKind::UInt, "0", "segundos por tarea (se ignora si hay `ms`)"),
+    ParamSpec::optional_no_default("ms", ParamKind::UInt, "milisegundos por tarea"),
+    ParamSpec::optional_no_default("ms_max", ParamKind::UInt, "si se indica, cada tarea dura un valor aleatorio en [ms, ms_max]"),
+    ParamSpec::optional("kind", ParamKind::Text, "sleep", "tipo de carga").one_of(&["sleep", "cpu", "io", "mixed"]),
+    ParamSpec::optional_no_default("concurrency", ParamKind::UInt, "tareas en paralelo (por defecto = tasks)")
+        .range(Some(1), None),
+];
+
+/// Tipo de trabajo de cada tarea de /loadtest
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadKind {
+    /// Duerme el hilo
+    Sleep,
+    /// Calcula Fibonacci en bucle
+    Cpu,
+    /// Escribe, sincroniza, lee y borra un archivo temporal en bucle
+    Io,
+    /// Alterna sleep, cpu e io según el índice de la tarea
+    Mixed,
+}
+
+/// Plan de una corrida de /loadtest, ya validado
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadtestPlan {
+    pub tasks: usize,
+    pub concurrency: usize,
+    pub kind: LoadKind,
+    /// Duración de cada tarea; si `min_ms` < `max_ms` se sortea en ese rango
+    pub min_ms: u64,
+    pub max_ms: u64,
 }
 
 /// Parsea y valida parámetros para /loadtest
 ///   - `tasks` es obligatorio y debe ser un entero > 0
-///   - `sleep` es obligatorio y debe ser un entero >= 0
-pub fn parse_loadtest_params(query: &str) -> Result<(usize, u64), String> {
-    let mut params = query
-        .split('&')
-        .filter_map(|p| {
-            let mut kv = p.splitn(2, '=');
-            match (kv.next(), kv.next()) {
-                (Some(k), Some(v)) => Some((k, v)),
-                _ => None,
-            }
-        })
-        .collect::<std::collections::HashMap<_, _>>();
-
-    let tasks = params
-        .remove("tasks")
-        .ok_or_else(|| "Parámetro 'tasks' requerido".to_string())?
-        .parse::<usize>()
-        .map_err(|_| "Parámetro 'tasks' debe ser un entero positivo".to_string())
-        .and_then(|n| if n > 0 {
-            Ok(n)
-        } else {
-            Err("Parámetro 'tasks' debe ser mayor que cero".to_string())
-        })?;
+///   - `sleep` (segundos) o `ms` dan la duración de cada tarea
+///   - Error 400 si `ms_max` < `ms`
+pub fn parse_loadtest_params(query: &str) -> Result<LoadtestPlan, String> {
+    loadtest_plan(&registry::validate(LOADTEST_PARAMS, query)?)
+}
 
-    let sleep_secs = params
-        .remove("sleep")
-        .ok_or_else(|| "Parámetro 'sleep' requerido".to_string())?
-        .parse::<u64>()
-        .map_err(|_| "Parámetro 'sleep' debe ser un entero no negativo".to_string())?;
+/// Arma el plan de /loadtest a partir de parámetros ya validados
+pub fn loadtest_plan(params: &registry::Params) -> Result<LoadtestPlan, String> {
+    let tasks = params.usize("tasks").unwrap_or(1);
+    let min_ms = params
+        .u64("ms")
+        .unwrap_or_else(|| params.u64("sleep").unwrap_or_default().saturating_mul(1000));
+    let max_ms = params.u64("ms_max").unwrap_or(min_ms);
+    if max_ms < min_ms {
+        return Err("Parámetro 'ms_max' no puede ser menor que 'ms'".to_string());
+    }
+    let kind = match params.str("kind") {
+        Some("cpu") => LoadKind::Cpu,
+        Some("io") => LoadKind::Io,
+        Some("mixed") => LoadKind::Mixed,
+        _ => LoadKind::Sleep,
+    };
 
-    Ok((tasks, sleep_secs))
+    Ok(LoadtestPlan {
+        tasks,
+        concurrency: params.usize("concurrency").unwrap_or(tasks),
+        kind,
+        min_ms,
+        max_ms,
+    })
 }
 
-/// Handler de /loadtest, recibe valores ya validados
-pub fn handle_loadtest(tasks: usize, sleep_secs: u64) -> String {
+/// Quema CPU calculando Fibonacci hasta agotar `dur`
+fn cpu_work(dur: Duration) {
     let start = Instant::now();
-    let mut handles = Vec::with_capacity(tasks);
-
-    for _ in 0..tasks {
-        let secs = sleep_secs;
-        handles.push(thread::spawn(move || {
-            thread::sleep(Duration::from_secs(secs));
-        }));
+    while start.elapsed() < dur {
+        std::hint::black_box(fib(std::hint::black_box(20)));
     }
+}
 
-    for h in handles {
-        let _ = h.join();
+/// Hace I/O real sobre un archivo temporal hasta agotar `dur`
+fn io_work(dur: Duration, task: usize) {
+    let path = std::env::temp_dir().join(format!("loadtest-{}-{}.tmp", std::process::id(), task));
+    let chunk = vec![b'x'; 64 * 1024];
+    let start = Instant::now();
+    while start.elapsed() < dur {
+        let written = std::fs::File::create(&path)
+            .and_then(|mut f| f.write_all(&chunk).and_then(|_| f.sync_all()));
+        if written.is_err() || std::fs::read(&path).is_err() {
+            break;
+        }
     }
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Respuesta JSON de /loadtest
+#[derive(Serialize)]
+struct LoadtestReport {
+    tasks: usize,
+    kind: LoadKind,
+    task_ms_min: u64,
+    task_ms_max: u64,
+    concurrency_requested: usize,
+    concurrency: usize,
+    peak_parallel: usize,
+    elapsed_ms: f64,
+    throughput_per_sec: f64,
+    latency_ms: LatencySummary,
+}
+
+/// Handler de /loadtest, recibe el plan ya validado. Las tareas corren en
+/// el executor acotado, con a lo sumo `concurrency` (y nunca más que los
+/// hilos del executor) en paralelo; se mide la latencia de cada una
+pub fn handle_loadtest(plan: &LoadtestPlan, executor: &Executor) -> String {
+    let latencies = Arc::new(Mutex::new(Vec::with_capacity(plan.tasks)));
+    let start = Instant::now();
 
-    let elapsed = start.elapsed().as_secs();
+    let (kind, min_ms, max_ms) = (plan.kind, plan.min_ms, plan.max_ms);
+    let samples = latencies.clone();
+    let peak = executor.run_batch(plan.tasks, plan.concurrency, move |i| {
+        let ms = if max_ms > min_ms { rand::thread_rng().gen_range(min_ms..=max_ms) } else { min_ms };
+        let dur = Duration::from_millis(ms);
+        let task_start = Instant::now();
+        let kind = match kind {
+            LoadKind::Mixed => [LoadKind::Sleep, LoadKind::Cpu, LoadKind::Io][i % 3],
+            k => k,
+        };
+        match kind {
+            LoadKind::Cpu => cpu_work(dur),
+            LoadKind::Io => io_work(dur, i),
+            _ => thread::sleep(dur),
+        }
+        let elapsed = task_start.elapsed().as_secs_f64() * 1000.0;
+        samples.lock().unwrap().push(elapsed);
+    });
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let mut samples = std::mem::take(&mut *latencies.lock().unwrap());
+
+    let report = LoadtestReport {
+        tasks: plan.tasks,
+        kind: plan.kind,
+        task_ms_min: plan.min_ms,
+        task_ms_max: plan.max_ms,
+        concurrency_requested: plan.concurrency,
+        concurrency: plan.concurrency.min(executor.size()).min(plan.tasks),
+        peak_parallel: peak,
+        elapsed_ms: elapsed * 1000.0,
+        throughput_per_sec: if elapsed > 0.0 { samples.len() as f64 / elapsed } else { 0.0 },
+        latency_ms: LatencySummary::from_samples(&mut samples),
+    };
+    let body = serde_json::to_string(&report).unwrap();
 
     format!(
         "HTTP/1.0 200 OK\r\n\
-         Content-Type: text/plain\r\n\r\n\
-         Carga de {} tarea(s) con {} seg de sleep completada en {} segundo(s)\n",
-        tasks, sleep_secs, elapsed
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        body
     )
 }
 
-/// Muestra ayuda: listado de rutas y parámetros
-pub fn handle_help() -> String {
-    let body = r#"Rutas disponibles:
-/status
-    -> GET: devuelve uptime y total_connections en JSON
+/// Límite de tiempo de un cómputo largo; los handlers lo consultan entre
+/// pasos y abandonan el trabajo si se agotó
+#[derive(Clone, Debug)]
+pub struct Deadline {
+    start: Instant,
+    budget: Duration,
+    cancel: Option<Cancel>,
+}
 
-/reverse?text=...
-    -> GET: invierte el texto
+/// El cómputo superó su límite de tiempo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedOut(pub Duration);
 
-/fibonacci?num=...
-    -> GET: calcula Fibonacci recursivo
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Deadline { start: Instant::now(), budget, cancel: None }
+    }
 
-/toupper?text=...
-    -> GET: convierte texto a MAYÚSCULAS
+    /// Corta también cuando el cliente abandona la petición
+    pub fn or_cancel(mut self, cancel: &Cancel) -> Self {
+        self.cancel = Some(cancel.clone());
+        self
+    }
+
+    pub fn check(&self) -> Result<(), TimedOut> {
+        if self.start.elapsed() > self.budget || self.cancel.as_ref().is_some_and(Cancel::is_cancelled) {
+            Err(TimedOut(self.budget))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Respuesta 503 para un cómputo que superó su límite de tiempo
+fn timed_out(path: &str, TimedOut(budget): TimedOut) -> String {
+    format!(
+        "HTTP/1.0 503 Service Unavailable\r\n\r\nTiempo agotado: {} superó {} ms\n",
+        path,
+        budget.as_millis()
+    )
+}
+
+/// Parámetro `timeout` común a los comandos de cálculo
+const TIMEOUT_PARAM: ParamSpec =
+    ParamSpec::optional_no_default("timeout", ParamKind::Duration, "tiempo máximo (tope: --compute-timeout)");
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+const SMALL_PRIMES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Miller–Rabin determinista para todo `u64` (bases = primos hasta 37)
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    SMALL_PRIMES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// Parámetros de /isprime
+pub const ISPRIME_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("n", ParamKind::UInt, "número a probar"),
+    TIMEOUT_PARAM,
+];
+
+/// Parsea y valida el parámetro n=N para /isprime
+///   - Err si falta `n` o no es un entero >= 0
+pub fn parse_isprime_param(query: &str) -> Result<u64, String> {
+    let params = registry::validate(ISPRIME_PARAMS, query)?;
+    Ok(params.u64("n").unwrap_or_default())
+}
+
+#[derive(Serialize)]
+struct IsPrimeResponse {
+    n: u64,
+    prime: bool,
+}
+
+/// Handler de /isprime, recibe `n` ya validado
+pub fn handle_isprime(n: u64, deadline: &Deadline) -> String {
+    let prime = is_prime(n);
+    match deadline.check() {
+        Ok(()) => json_ok(&IsPrimeResponse { n, prime }),
+        Err(t) => timed_out("/isprime", t),
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Un divisor no trivial de `n` (compuesto e impar) por Pollard rho
+fn pollard_rho(n: u64, deadline: &Deadline) -> Result<u64, TimedOut> {
+    for c in 1..n {
+        let f = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        let mut steps = 0u32;
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+            steps = steps.wrapping_add(1);
+            if steps.is_multiple_of(1024) {
+                deadline.check()?;
+            }
+        }
+        if d != n {
+            return Ok(d);
+        }
+    }
+    Ok(n)
+}
+
+/// Factores primos de `n` en orden, con repetición (vacío para 0 y 1)
+pub fn factorize(mut n: u64, deadline: &Deadline) -> Result<Vec<u64>, TimedOut> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return Ok(factors);
+    }
+    for p in SMALL_PRIMES {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+    }
+    let mut pending = vec![n];
+    while let Some(m) = pending.pop() {
+        if m == 1 {
+            continue;
+        }
+        if is_prime(m) {
+            factors.push(m);
+            continue;
+        }
+        let d = pollard_rho(m, deadline)?;
+        pending.push(d);
+        pending.push(m / d);
+    }
+    factors.sort_unstable();
+    Ok(factors)
+}
+
+/// Parámetros de /factor
+pub const FACTOR_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("n", ParamKind::UInt, "número a factorizar"),
+    TIMEOUT_PARAM,
+];
+
+/// Parsea y valida el parámetro n=N para /factor
+///   - Err si falta `n` o no es un entero >= 0
+pub fn parse_factor_param(query: &str) -> Result<u64, String> {
+    let params = registry::validate(FACTOR_PARAMS, query)?;
+    Ok(params.u64("n").unwrap_or_default())
+}
+
+#[derive(Serialize)]
+struct FactorResponse {
+    n: u64,
+    factors: Vec<u64>,
+}
+
+/// Handler de /factor, recibe `n` ya validado
+pub fn handle_factor(n: u64, deadline: &Deadline) -> String {
+    match factorize(n, deadline) {
+        Ok(factors) => json_ok(&FactorResponse { n, factors }),
+        Err(t) => timed_out("/factor", t),
+    }
+}
+
+/// Dígitos de pi por el algoritmo spigot de Rabinowitz–Wagon, p. ej.
+/// `pi_digits(5) == "3.1415"`
+pub fn pi_digits(digits: usize, deadline: &Deadline) -> Result<String, TimedOut> {
+    // Unos dígitos de más para que los últimos pedidos sean exactos
+    let computed = digits + 5;
+    let len = computed * 10 / 3 + 2;
+    let mut a = vec![2u64; len];
+    let mut out = String::with_capacity(digits + 2);
+    let (mut predigit, mut nines) = (None::<u64>, 0);
+
+    let push = |out: &mut String, d: u64| out.push(char::from(b'0' + d as u8));
+
+    for _ in 0..computed {
+        deadline.check()?;
+        let mut q = 0;
+        for i in (1..len).rev() {
+            let x = 10 * a[i] + q * (i as u64 + 1);
+            let den = 2 * i as u64 + 1;
+            a[i] = x % den;
+            q = x / den;
+        }
+        let x = 10 * a[0] + q;
+        a[0] = x % 10;
+        q = x / 10;
+
+        match q {
+            9 => nines += 1,
+            10 => {
+                if let Some(p) = predigit {
+                    push(&mut out, p + 1);
+                }
+                out.extend(std::iter::repeat_n('0', nines));
+                predigit = Some(0);
+                nines = 0;
+            }
+            _ => {
+                if let Some(p) = predigit {
+                    push(&mut out, p);
+                }
+                out.extend(std::iter::repeat_n('9', nines));
+                predigit = Some(q);
+                nines = 0;
+            }
+        }
+    }
+    if let Some(p) = predigit {
+        push(&mut out, p);
+    }
+    out.extend(std::iter::repeat_n('9', nines));
+    out.truncate(digits);
+    if out.len() > 1 {
+        out.insert(1, '.');
+    }
+    Ok(out)
+}
+
+/// Parámetros de /pi
+pub const PI_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("digits", ParamKind::UInt, "dígitos a calcular (contando el 3)").range(Some(1), Some(20_000)),
+    TIMEOUT_PARAM,
+];
+
+/// Parsea y valida el parámetro digits=N para /pi
+///   - Err si falta `digits` o no está entre 1 y 20000
+pub fn parse_pi_param(query: &str) -> Result<usize, String> {
+    let params = registry::validate(PI_PARAMS, query)?;
+    Ok(params.usize("digits").unwrap_or(1))
+}
 
-/createfile?name=...&content=...&repeat=...
-    -> GET: crea archivo con contenido repetido
+/// Costo estimado de /pi: cuadrático en la cantidad de dígitos
+pub fn pi_cost(params: &registry::Params) -> f64 {
+    let digits = params.u64("digits").unwrap_or(1) as f64;
+    digits * digits * 3e-5
+}
+
+/// Handler de /pi, recibe `digits` ya validado
+pub fn handle_pi(digits: usize, deadline: &Deadline) -> String {
+    match pi_digits(digits, deadline) {
+        Ok(pi) => format!(
+            "HTTP/1.0 200 OK\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             {}\n",
+            pi
+        ),
+        Err(t) => timed_out("/pi", t),
+    }
+}
+
+/// Parámetros de /mandelbrot
+pub const MANDELBROT_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("width", ParamKind::UInt, "256", "ancho en píxeles").range(Some(1), Some(2048)),
+    ParamSpec::optional("height", ParamKind::UInt, "192", "alto en píxeles").range(Some(1), Some(2048)),
+    ParamSpec::optional("max_iter", ParamKind::UInt, "255", "iteraciones por píxel").range(Some(1), Some(100_000)),
+    TIMEOUT_PARAM,
+];
+
+/// Tamaño y detalle de la imagen de /mandelbrot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mandelbrot {
+    pub width: usize,
+    pub height: usize,
+    pub max_iter: u32,
+}
+
+/// Parsea y valida los parámetros de /mandelbrot (todos opcionales)
+pub fn parse_mandelbrot_params(query: &str) -> Result<Mandelbrot, String> {
+    Ok(mandelbrot_args(&registry::validate(MANDELBROT_PARAMS, query)?))
+}
+
+pub fn mandelbrot_args(params: &registry::Params) -> Mandelbrot {
+    Mandelbrot {
+        width: params.usize("width").unwrap_or(256),
+        height: params.usize("height").unwrap_or(192),
+        max_iter: params.u64("max_iter").unwrap_or(255) as u32,
+    }
+}
 
-/deletefile?name=...
-    -> GET: elimina archivo
+/// Costo estimado de /mandelbrot: píxeles por iteraciones máximas
+pub fn mandelbrot_cost(params: &registry::Params) -> f64 {
+    let m = mandelbrot_args(params);
+    (m.width * m.height) as f64 * m.max_iter as f64 * 1e-5
+}
+
+/// Handler de /mandelbrot: imagen PGM en texto (P2) del plano
+/// [-2.5, 1] x [-1, 1]; los puntos del conjunto quedan en negro
+pub fn handle_mandelbrot(m: &Mandelbrot, deadline: &Deadline) -> String {
+    let mut body = format!("P2\n{} {}\n255\n", m.width, m.height);
+    for row in 0..m.height {
+        if let Err(t) = deadline.check() {
+            return timed_out("/mandelbrot", t);
+        }
+        let ci = 1.0 - 2.0 * row as f64 / m.height as f64;
+        let line: Vec<String> = (0..m.width)
+            .map(|col| {
+                let cr = -2.5 + 3.5 * col as f64 / m.width as f64;
+                let (mut zr, mut zi, mut iter) = (0.0f64, 0.0f64, 0);
+                while iter < m.max_iter && zr * zr + zi * zi <= 4.0 {
+                    (zr, zi) = (zr * zr - zi * zi + cr, 2.0 * zr * zi + ci);
+                    iter += 1;
+                }
+                let shade = if iter == m.max_iter { 0 } else { 255 - 255 * iter / m.max_iter };
+                shade.to_string()
+            })
+            .collect();
+        body.push_str(&line.join(" "));
+        body.push('\n');
+    }
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: image/x-portable-graymap\r\n\r\n\
+         {}",
+        body
+    )
+}
 
-/random?count=...&min=...&max=...
-    -> GET: genera array JSON de números aleatorios
+/// Parámetros de /matrixmul
+pub const MATRIXMUL_PARAMS: &[ParamSpec] = &[
+    ParamSpec::required("size", ParamKind::UInt, "lado de las matrices cuadradas").range(Some(1), Some(2048)),
+    ParamSpec::optional_no_default("seed", ParamKind::UInt, "semilla de las matrices"),
+    TIMEOUT_PARAM,
+];
+
+/// Parsea y valida size=N (y seed=S opcional) para /matrixmul
+pub fn parse_matrixmul_params(query: &str) -> Result<(usize, Option<u64>), String> {
+    let params = registry::validate(MATRIXMUL_PARAMS, query)?;
+    Ok((params.usize("size").unwrap_or(1), params.u64("seed")))
+}
 
-/timestamp
-    -> GET: hora actual UTC en texto plano
+#[derive(Serialize)]
+struct MatrixMulResponse {
+    size: usize,
+    seed: u64,
+    /// Suma de todos los elementos del producto, para comparar corridas
+    checksum: f64,
+    elapsed_ms: f64,
+}
 
-/hash?text=...
-    -> GET: SHA-256 del texto en hex
+/// Costo estimado de /matrixmul: cúbico en el lado
+pub fn matrixmul_cost(params: &registry::Params) -> f64 {
+    (params.u64("size").unwrap_or(1) as f64).powi(3) * 1e-6
+}
 
-/simulate?seconds=...&task=...
-    -> GET: simula tarea con delay
+/// Handler de /matrixmul: multiplica dos matrices aleatorias (valores en
+/// [-1, 1) generados con `seed`) y devuelve un checksum del producto
+pub fn handle_matrixmul(size: usize, seed: u64, deadline: &Deadline) -> String {
+    let start = Instant::now();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut random_matrix = || -> Vec<f64> { (0..size * size).map(|_| rng.gen_range(-1.0..1.0)).collect() };
+    let a = random_matrix();
+    let b = random_matrix();
+    let mut c = vec![0.0; size * size];
+
+    for i in 0..size {
+        if let Err(t) = deadline.check() {
+            return timed_out("/matrixmul", t);
+        }
+        for k in 0..size {
+            let aik = a[i * size + k];
+            let (row, b_row) = (&mut c[i * size..(i + 1) * size], &b[k * size..(k + 1) * size]);
+            for (cij, bkj) in row.iter_mut().zip(b_row) {
+                *cij += aik * bkj;
+            }
+        }
+    }
 
-/sleep?seconds=...
-    -> GET: simula retardo simple
+    json_ok(&MatrixMulResponse {
+        size,
+        seed,
+        checksum: c.iter().sum(),
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+    })
+}
 
-/loadtest?tasks=...&sleep=...
-    -> GET: carga concurrente de tareas con sleep
+/// Parámetros de /help
+pub const HELP_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("format", ParamKind::Text, "text", "formato de salida").one_of(&["text", "json"]),
+];
 
-/help
-    -> GET: muestra esta ayuda
-"#;
-    // Cabecera con charset=utf-8 y cuerpo ASCII-only
+/// Muestra ayuda: listado de rutas y parámetros, generado desde el registro
+pub fn handle_help(registry: &Registry) -> String {
+    let body = registry::render_help(registry);
     format!(
         "HTTP/1.0 200 OK\r\n\
          Content-Type: text/plain; charset=utf-8\r\n\r\n\
@@ -468,6 +1441,28 @@ pub fn handle_help() -> String {
     )
 }
 
+/// Ayuda en formato JSON (`/help?format=json`), con la misma información que /help
+pub fn handle_help_json(registry: &Registry) -> String {
+    let body = registry::help_json(registry);
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}",
+        body
+    )
+}
+
+/// Documento OpenAPI 3 de todas las rutas
+pub fn handle_openapi(registry: &Registry) -> String {
+    let body = crate::openapi::document(registry);
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}",
+        body
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -503,7 +1498,7 @@ mod tests {
     fn handle_toupper_response_contains_uppercase() {
         let resp = handle_toupper("rust");
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
-        assert!(resp.contains("RUST\n"));
+        assert!(resp.contains("\"result\":\"RUST\""));
     }
 
     #[test]
@@ -587,7 +1582,11 @@ mod tests {
     // Tests para /sleep
     #[test]
     fn parse_sleep_success() {
-        assert_eq!(parse_sleep_param("seconds=3").unwrap(), 3);
+        assert_eq!(parse_sleep_param("seconds=3").unwrap(), Delay::fixed(Duration::from_secs(3)));
+        assert_eq!(parse_sleep_param("ms=250").unwrap().requested, Duration::from_millis(250));
+        let d = parse_sleep_param("duration=1.5s&jitter=100ms&seconds=9").unwrap();
+        assert_eq!(d.requested, Duration::from_millis(1500));
+        assert_eq!(d.jitter, Duration::from_millis(100));
     }
 
     #[test]
@@ -602,11 +1601,42 @@ mod tests {
         assert_eq!(err, "Parámetro 'seconds' debe ser un entero no negativo");
     }
 
+    #[test]
+    fn parse_sleep_invalid_duration() {
+        let err = parse_sleep_param("duration=soon").unwrap_err();
+        assert_eq!(err, "Parámetro 'duration' debe ser una duración (p. ej. 250ms, 1.5s, 2m)");
+    }
+
+    #[test]
+    fn handle_sleep_reports_measured_duration() {
+        let delay = Delay { requested: Duration::from_millis(20), jitter: Duration::from_millis(5) };
+        let resp = handle_sleep(&delay);
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let report: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(report["requested_ms"], 20.0);
+        let target = report["target_ms"].as_f64().unwrap();
+        assert!((15.0..=25.0).contains(&target));
+        assert!(report["measured_ms"].as_f64().unwrap() >= target);
+        assert_eq!(report["message"], "Espera de 0.02 segundo(s) completada");
+    }
+
+    #[test]
+    fn sliced_delay_does_not_accumulate_sleep_overshoot() {
+        // Porciones de 1 ms como un round-robin muy fino: cada `sleep` se
+        // pasa un poco, pero el avance sale del reloj
+        let delay = Delay::fixed(Duration::from_millis(300));
+        let mut work = DelayWork::new(&delay);
+        while !work.advance(SimulateMode::Sleep, Duration::from_millis(1), &Cancel::default()) {}
+        let measured = work.started.elapsed();
+        assert!(measured >= Duration::from_millis(300));
+        assert!(measured < Duration::from_millis(310), "{:?}", measured);
+    }
+
     // Tests para /simulate
     #[test]
     fn parse_simulate_success() {
         let (s, t) = parse_simulate_params("seconds=2&task=test").unwrap();
-        assert_eq!(s, 2);
+        assert_eq!(s.requested, Duration::from_secs(2));
         assert_eq!(t, "test".to_string());
     }
 
@@ -622,12 +1652,38 @@ mod tests {
         assert_eq!(err, "Parámetro 'task' requerido");
     }
 
+    #[test]
+    fn handle_simulate_cpu_mode_does_work() {
+        let delay = Delay::fixed(Duration::from_millis(20));
+        let resp = handle_simulate(&delay, "c".into(), SimulateMode::Cpu);
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let report: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(report["mode"], "cpu");
+        assert!(report["iterations"].as_u64().unwrap() > 0);
+        assert!(report["measured_ms"].as_f64().unwrap() >= 20.0);
+
+        let resp = handle_simulate(&delay, "s".into(), SimulateMode::Sleep);
+        assert!(resp.contains("\"iterations\":0"));
+    }
+
     // Tests para /loadtest
     #[test]
     fn parse_loadtest_success() {
-        let (tasks, secs) = parse_loadtest_params("tasks=4&sleep=1").unwrap();
-        assert_eq!(tasks, 4);
-        assert_eq!(secs, 1);
+        let plan = parse_loadtest_params("tasks=4&sleep=1").unwrap();
+        assert_eq!(plan.tasks, 4);
+        assert_eq!(plan.concurrency, 4);
+        assert_eq!(plan.kind, LoadKind::Sleep);
+        assert_eq!((plan.min_ms, plan.max_ms), (1000, 1000));
+    }
+
+    #[test]
+    fn parse_loadtest_ms_range_and_kind() {
+        let plan = parse_loadtest_params("tasks=2&ms=5&ms_max=20&kind=cpu&sleep=9").unwrap();
+        assert_eq!((plan.min_ms, plan.max_ms), (5, 20));
+        assert_eq!(plan.kind, LoadKind::Cpu);
+        let err = parse_loadtest_params("tasks=2&ms=5&ms_max=1").unwrap_err();
+        assert_eq!(err, "Parámetro 'ms_max' no puede ser menor que 'ms'");
+        assert!(parse_loadtest_params("tasks=2&kind=gpu").is_err());
     }
 
     #[test]
@@ -645,14 +1701,21 @@ mod tests {
     // Test para handle_random: debe generar un JSON con el número correcto de elementos
     #[test]
     fn handle_random_response_length() {
-        let resp = handle_random(10, 1, 5);
+        let resp = handle_random(10, 1, 5, 42);
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        assert!(resp.contains("X-Random-Seed: 42\r\n"));
         // tras la cabecera y salto de línea, debe haber un array de 10 elementos
         let body = resp.split("\r\n\r\n").nth(1).unwrap();
         let vec: Vec<i64> = serde_json::from_str(body.trim()).unwrap();
         assert_eq!(vec.len(), 10);
         // cada elemento debe estar en [1,5]
-        assert!(vec.iter().all(|&x| x >= 1 && x <= 5));
+        assert!(vec.iter().all(|&x| (1..=5).contains(&x)));
+    }
+
+    #[test]
+    fn handle_random_same_seed_same_sequence() {
+        assert_eq!(handle_random(20, -100, 100, 7), handle_random(20, -100, 100, 7));
+        assert_ne!(handle_random(20, -100, 100, 7), handle_random(20, -100, 100, 8));
     }
 
     // Test para handle_hash: el SHA-256 de "abc" es altamente conocido
@@ -670,7 +1733,7 @@ mod tests {
     #[test]
     fn handle_sleep_zero() {
         let start = Instant::now();
-        let resp = handle_sleep(0);
+        let resp = handle_sleep(&Delay::fixed(Duration::ZERO));
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
         assert!(resp.contains("Espera de 0 segundo(s) completada"));
         assert!(start.elapsed().as_secs() == 0);
@@ -679,23 +1742,41 @@ mod tests {
     // handle_simulate con 0 segundos retorna inmediatamente
     #[test]
     fn handle_simulate_zero() {
-        let resp = handle_simulate(0, "x".into());
+        let resp = handle_simulate(&Delay::fixed(Duration::ZERO), "x".into(), SimulateMode::Sleep);
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
         assert!(resp.contains("Tarea 'x' completada en 0 segundo(s)"));
     }
 
-    // handle_loadtest con 1 tarea y 0 sleep retorna 0 segundos
+    // handle_loadtest con 1 tarea y 0 sleep: reporte JSON con latencias
     #[test]
     fn handle_loadtest_zero() {
-        let resp = handle_loadtest(1, 0);
+        let plan = parse_loadtest_params("tasks=1&sleep=0").unwrap();
+        let resp = handle_loadtest(&plan, &Executor::new("test", 1));
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
-        assert!(resp.contains("Carga de 1 tarea(s) con 0 seg de sleep completada en 0 segundo(s)"));
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let report: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(report["tasks"], 1);
+        assert_eq!(report["latency_ms"]["count"], 1);
+    }
+
+    #[test]
+    fn handle_loadtest_every_kind() {
+        let ex = Executor::new("test", 3);
+        for kind in ["sleep", "cpu", "io", "mixed"] {
+            let plan = parse_loadtest_params(&format!("tasks=3&ms=2&kind={}", kind)).unwrap();
+            let resp = handle_loadtest(&plan, &ex);
+            let body = resp.split("\r\n\r\n").nth(1).unwrap();
+            let report: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+            assert_eq!(report["kind"], kind);
+            assert!(report["latency_ms"]["min"].as_f64().unwrap() >= 2.0);
+            assert!(report["latency_ms"]["p99"].as_f64().unwrap() <= report["latency_ms"]["max"].as_f64().unwrap());
+        }
     }
 
     // handle_help contiene al menos la ruta /status y /help
     #[test]
     fn handle_help_contains_routes() {
-        let resp = handle_help();
+        let resp = handle_help(&crate::router::builtin_registry());
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
         let body = resp.split("\r\n\r\n").nth(1).unwrap();
         assert!(body.contains("/status"));
@@ -706,7 +1787,7 @@ mod tests {
     fn direct_handle_reverse() {
         let resp = handle_reverse("Rust");
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
-        assert!(resp.contains("tsuR\n"));
+        assert!(resp.contains("\"result\":\"tsuR\""));
     }
 
     // Direct tests para handle_createfile y handle_deletefile
@@ -733,8 +1814,141 @@ mod tests {
 
     #[test]
     fn handle_loadtest_multiple_zero() {
-        let resp = handle_loadtest(2, 0);
+        let plan = parse_loadtest_params("tasks=2&sleep=0").unwrap();
+        let resp = handle_loadtest(&plan, &Executor::new("test", 4));
         assert!(resp.starts_with("HTTP/1.0 200 OK"));
-        assert!(resp.contains("Carga de 2 tarea(s) con 0 seg de sleep completada en 0 segundo(s)\n"));
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let report: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(report["concurrency"], 2);
+        assert_eq!(report["latency_ms"]["count"], 2);
+    }
+
+    // Tests de los comandos de cálculo
+    fn forever() -> Deadline {
+        Deadline::after(Duration::from_secs(60))
+    }
+
+    #[test]
+    fn parse_math_params() {
+        assert_eq!(parse_isprime_param("n=97").unwrap(), 97);
+        assert_eq!(parse_factor_param("").unwrap_err(), "Parámetro 'n' requerido");
+        assert_eq!(parse_pi_param("digits=0").unwrap_err(), "Parámetro 'digits' debe ser mayor que cero");
+        assert_eq!(parse_mandelbrot_params("").unwrap(), Mandelbrot { width: 256, height: 192, max_iter: 255 });
+        assert_eq!(parse_matrixmul_params("size=4&seed=9").unwrap(), (4, Some(9)));
+        assert!(parse_matrixmul_params("size=4&timeout=pronto").is_err());
+    }
+
+    #[test]
+    fn is_prime_known_values() {
+        let primes: Vec<u64> = (0..50).filter(|&n| is_prime(n)).collect();
+        assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]);
+        assert!(is_prime(18_446_744_073_709_551_557)); // mayor primo u64
+        assert!(!is_prime(3_215_031_751)); // pseudoprimo fuerte en bases 2, 3, 5 y 7
+    }
+
+    #[test]
+    fn factorize_known_values() {
+        assert_eq!(factorize(1, &forever()).unwrap(), Vec::<u64>::new());
+        assert_eq!(factorize(360, &forever()).unwrap(), [2, 2, 2, 3, 3, 5]);
+        assert_eq!(factorize(600_851_475_143, &forever()).unwrap(), [71, 839, 1471, 6857]);
+        assert_eq!(factorize(4_294_967_291 * 4_294_967_279, &forever()).unwrap(), [4_294_967_279, 4_294_967_291]);
+    }
+
+    #[test]
+    fn pi_digits_known_prefix() {
+        assert_eq!(pi_digits(1, &forever()).unwrap(), "3");
+        assert_eq!(
+            pi_digits(50, &forever()).unwrap(),
+            "3.1415926535897932384626433832795028841971693993751"
+        );
+    }
+
+    #[test]
+    fn math_handlers_respect_deadline() {
+        let expired = Deadline::after(Duration::ZERO);
+        sleep(Duration::from_millis(2));
+        let resp = handle_pi(1000, &expired);
+        assert!(resp.starts_with("HTTP/1.0 503 Service Unavailable"), "{}", resp);
+        assert!(handle_matrixmul(64, 1, &expired).starts_with("HTTP/1.0 503"));
+        let m = Mandelbrot { width: 8, height: 8, max_iter: 10 };
+        assert!(handle_mandelbrot(&m, &expired).starts_with("HTTP/1.0 503"));
+    }
+
+    #[test]
+    fn mandelbrot_is_a_pgm_image() {
+        let resp = handle_mandelbrot(&Mandelbrot { width: 7, height: 4, max_iter: 50 }, &forever());
+        assert!(resp.contains("Content-Type: image/x-portable-graymap"));
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let mut lines = body.lines();
+        assert_eq!(lines.next(), Some("P2"));
+        assert_eq!(lines.next(), Some("7 4"));
+        assert_eq!(lines.next(), Some("255"));
+        let pixels: Vec<u32> = lines.flat_map(|l| l.split(' ')).map(|p| p.parse().unwrap()).collect();
+        assert_eq!(pixels.len(), 28);
+        assert!(pixels.contains(&0));
+    }
+
+    #[test]
+    fn matrixmul_is_reproducible_by_seed() {
+        let checksum = |seed| {
+            let resp = handle_matrixmul(16, seed, &forever());
+            let body = resp.split("\r\n\r\n").nth(1).unwrap();
+            serde_json::from_str::<serde_json::Value>(body.trim()).unwrap()["checksum"].clone()
+        };
+        assert_eq!(checksum(5), checksum(5));
+        assert_ne!(checksum(5), checksum(6));
+    }
+
+    // Tests de los comandos de texto
+    fn text_result(op: TextOp, text: &str) -> serde_json::Value {
+        apply_text_op(op, text).unwrap()
+    }
+
+    #[test]
+    fn reverse_keeps_graphemes_together() {
+        // "e" + acento combinado y una familia con ZWJ
+        let text = "ae\u{301}👨‍👩‍👧z";
+        assert_eq!(text_result(TextOp::Reverse, text), "z👨‍👩‍👧e\u{301}a");
+        let resp = handle_reverse(text);
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(json["op"], "reverse");
+        assert_eq!(json["input"]["graphemes"], 4);
+        assert_eq!(json["input"]["chars"], 9);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn text_ops_results() {
+        assert_eq!(text_result(TextOp::ToLower, "ÁRBOL"), "árbol");
+        assert_eq!(text_result(TextOp::TitleCase, "hola mUNDO, ¿qué tal?"), "Hola Mundo, ¿Qué Tal?");
+        assert_eq!(text_result(TextOp::WordCount, "uno, dos\ntres"), 3);
+        assert_eq!(text_result(TextOp::CharCount, "ñe\u{301}"), 2);
+        assert_eq!(text_result(TextOp::Palindrome, "Anita lava la tina"), true);
+        assert_eq!(text_result(TextOp::Palindrome, "Dábale arroz a la zorra el abad"), true);
+        assert_eq!(text_result(TextOp::Palindrome, "rust"), false);
+        assert_eq!(text_result(TextOp::Base64Encode, "hola"), "aG9sYQ==");
+        assert_eq!(text_result(TextOp::Base64Decode, "aG9sYQ=="), "hola");
+        assert_eq!(text_result(TextOp::UrlEncode, "a b&ñ"), "a%20b%26%C3%B1");
+        assert_eq!(text_result(TextOp::UrlDecode, "a%20b%26%C3%B1"), "a b&ñ");
+    }
+
+    #[test]
+    fn text_ops_invalid_input() {
+        assert_eq!(apply_text_op(TextOp::Base64Decode, "@@").unwrap_err(), "El texto no es base64 válido");
+        assert_eq!(apply_text_op(TextOp::Base64Decode, "/w==").unwrap_err(), "El base64 no contiene texto UTF-8");
+        assert!(handle_text(TextOp::UrlDecode, "%FF").starts_with("HTTP/1.0 400 Bad Request"));
+    }
+
+    #[test]
+    fn text_input_from_query_or_body() {
+        let mut req = Request::new("POST", "/reverse");
+        req.body = "desde el cuerpo".as_bytes().to_vec();
+        assert_eq!(text_input(&req).unwrap(), "desde el cuerpo");
+
+        req.params = registry::validate(TEXT_PARAMS, "text=hola+mundo%21").unwrap();
+        assert_eq!(text_input(&req).unwrap(), "hola mundo!");
+
+        let req = Request::new("POST", "/reverse");
+        assert_eq!(text_input(&req).unwrap_err(), "Parámetro 'text' requerido");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::registry::Params;
+use std::any::Any;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Petición HTTP ya parseada que reciben los handlers
+#[derive(Clone, Debug, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub peer: Option<SocketAddr>,
+    /// Parámetros validados contra el esquema de la ruta (los completa el router)
+    pub params: Params,
+    /// Conexión del cliente, para handlers que envían la respuesta por partes
+    pub sink: Option<ResponseSink>,
+    /// Estado de un handler cooperativo entre una porción de tiempo y la siguiente
+    pub resume: Option<Resume>,
+    /// El handler cedió el hilo sin terminar: la petición vuelve a la cola
+    pub yielded: bool,
+    /// Recursos que los middleware retienen mientras la petición cede el
+    /// hilo (p. ej. el hueco de concurrencia del rate limiter); se liberan
+    /// al terminar la petición, aunque se descarte en la cola
+    pub held: Vec<Held>,
+    /// Se marca si el cliente se desconecta mientras la petición se atiende
+    pub cancel: Cancel,
+}
+
+/// Aviso de que el cliente abandonó la petición. Los handlers largos lo
+/// consultan para dejar de trabajar; la respuesta ya no se envía
+#[derive(Clone, Debug, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Estado guardado por un handler cooperativo (ver `registry::Step`)
+pub type Resume = Arc<Mutex<dyn Any + Send>>;
+
+/// Recurso que se libera al soltarse (ver `Request.held`)
+pub type Held = Arc<dyn Any + Send + Sync>;
+
+/// Destino donde un handler puede escribir la respuesta directamente (p. ej.
+/// respuestas muy grandes). Si lo usa, el handler devuelve una respuesta
+/// vacía para que el servidor no escriba nada más
+#[derive(Clone)]
+pub struct ResponseSink(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl ResponseSink {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        ResponseSink(Arc::new(Mutex::new(Box::new(writer))))
+    }
+}
+
+impl Write for ResponseSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+impl fmt::Debug for ResponseSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResponseSink")
+    }
+}
+
+impl Request {
+    /// Petición GET sin cabeceras ni cuerpo, p. ej. `/fibonacci?num=10`
+    pub fn get(path_and_query: &str) -> Self {
+        Self::new("GET", path_and_query)
+    }
+
+    pub fn new(method: &str, path_and_query: &str) -> Self {
+        let (path, query) = path_and_query.split_once('?').unwrap_or((path_and_query, ""));
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Parsea la línea de petición, las cabeceras y el cuerpo de un buffer crudo
+    ///   - Err si falta la línea de petición o no tiene método y ruta
+    pub fn parse(raw: &[u8]) -> Result<Request, String> {
+        let (head, body) = match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(i) => (&raw[..i], &raw[i + 4..]),
+            None => (raw, &[][..]),
+        };
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.lines();
+
+        let first_line = lines.next().unwrap_or("");
+        let components: Vec<&str> = first_line.split_whitespace().collect();
+        if components.len() < 2 {
+            return Err("Línea de petición inválida".to_string());
+        }
+
+        let mut req = Request::new(components[0], components[1]);
+        for line in lines {
+            if let Some((k, v)) = line.split_once(':') {
+                req.headers.push((k.trim().to_string(), v.trim().to_string()));
+            }
+        }
+        req.body = body.to_vec();
+        Ok(req)
+    }
+
+    /// Valor de una cabecera (sin distinguir mayúsculas)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Ruta con query, tal como llegó en la línea de petición
+    pub fn path_and_query(&self) -> String {
+        if self.query.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}?{}", self.path, self.query)
+        }
+    }
+}
+
+/// Añade una cabecera a una respuesta HTTP ya formateada, justo después
+/// de la línea de estado
+pub fn with_header(resp: String, name: &str, value: &str) -> String {
+    match resp.find("\r\n") {
+        Some(i) => format!("{}\r\n{}: {}{}", &resp[..i], name, value, &resp[i..]),
+        None => resp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_request_line_headers_and_body() {
+        let raw = b"POST /reverse?text=abc HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nhi";
+        let req = Request::parse(raw).unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/reverse");
+        assert_eq!(req.query, "text=abc");
+        assert_eq!(req.header("content-length"), Some("2"));
+        assert_eq!(req.body, b"hi");
+        assert_eq!(req.path_and_query(), "/reverse?text=abc");
+    }
+
+    #[test]
+    fn parse_invalid_request_line() {
+        assert!(Request::parse(b"GARBAGE\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn with_header_inserts_after_status_line() {
+        let resp = with_header("HTTP/1.0 200 OK\r\n\r\nx".to_string(), "X-A", "1");
+        assert_eq!(resp, "HTTP/1.0 200 OK\r\nX-A: 1\r\n\r\nx");
+    }
+}
@@ -0,0 +1,22 @@
+pub mod config;
+pub mod state;
+pub mod executor;
+pub mod cputime;
+pub mod http;
+pub mod disconnect;
+pub mod frontend;
+pub mod registry;
+pub mod middleware;
+pub mod ratelimit;
+pub mod singleflight;
+pub mod cache;
+pub mod chaos;
+pub mod scheduler;
+pub mod pool;
+pub mod trace;
+pub mod router;
+pub mod handlers;
+pub mod random;
+pub mod openapi;
+pub mod server;
+pub mod stats;
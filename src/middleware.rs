@@ -0,0 +1,114 @@
+use crate::http::{self, Request};
+use crate::state::SharedState;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Capa que envuelve el manejo de una petición. Puede inspeccionar o modificar
+/// la petición, cortar la cadena devolviendo su propia respuesta sin llamar a
+/// `next`, o transformar la respuesta que devuelve `next.run(..)`
+pub trait Middleware: Send + Sync {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(&mut Request, &SharedState, Next<'_>) -> String + Send + Sync,
+{
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        self(req, state, next)
+    }
+}
+
+/// Punto final de una cadena de middleware (el router o el handler de la ruta)
+pub type Endpoint<'a> = &'a dyn Fn(&mut Request, &SharedState) -> String;
+
+/// Resto de la cadena por ejecutar
+pub struct Next<'a> {
+    chain: &'a [Arc<dyn Middleware>],
+    endpoint: Endpoint<'a>,
+}
+
+impl<'a> Next<'a> {
+    pub fn new(chain: &'a [Arc<dyn Middleware>], endpoint: Endpoint<'a>) -> Self {
+        Next { chain, endpoint }
+    }
+
+    /// Ejecuta el siguiente middleware, o el punto final si no quedan
+    pub fn run(self, req: &mut Request, state: &SharedState) -> String {
+        match self.chain.split_first() {
+            Some((mw, rest)) => mw.handle(req, state, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(req, state),
+        }
+    }
+}
+
+/// Cuenta cada petición recibida en `ServerState.total_connections`
+pub struct ConnectionCounter;
+
+impl Middleware for ConnectionCounter {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        {
+            let mut st = state.lock().unwrap();
+            st.total_connections += 1;
+        }
+        next.run(req, state)
+    }
+}
+
+/// Añade la cabecera `X-Response-Time` con la duración del manejo en ms
+pub struct Timing;
+
+impl Middleware for Timing {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        let start = Instant::now();
+        let resp = next.run(req, state);
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        http::with_header(resp, "X-Response-Time", &format!("{:.3}ms", ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::new_state;
+
+    fn endpoint(_: &mut Request, _: &SharedState) -> String {
+        "HTTP/1.0 200 OK\r\n\r\nfin\n".to_string()
+    }
+
+    #[test]
+    fn chain_runs_in_order_and_can_short_circuit() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> = vec![
+            Arc::new(|req: &mut Request, st: &SharedState, next: Next<'_>| {
+                req.query.push_str("&a=1");
+                next.run(req, st)
+            }),
+            Arc::new(|req: &mut Request, st: &SharedState, next: Next<'_>| {
+                if req.query.contains("stop") {
+                    return "HTTP/1.0 403 Forbidden\r\n\r\n".to_string();
+                }
+                next.run(req, st)
+            }),
+        ];
+
+        let mut req = Request::get("/x?q=1");
+        let resp = Next::new(&chain, &endpoint).run(&mut req, &state);
+        assert!(resp.contains("fin"));
+        assert_eq!(req.query, "q=1&a=1");
+
+        let mut req = Request::get("/x?stop");
+        let resp = Next::new(&chain, &endpoint).run(&mut req, &state);
+        assert!(resp.starts_with("HTTP/1.0 403 Forbidden"));
+    }
+
+    #[test]
+    fn connection_counter_and_timing() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> = vec![Arc::new(ConnectionCounter), Arc::new(Timing)];
+        let resp = Next::new(&chain, &endpoint).run(&mut Request::get("/x"), &state);
+        assert_eq!(state.lock().unwrap().total_connections, 1);
+        assert!(resp.contains("X-Response-Time: "));
+        assert!(resp.ends_with("\r\n\r\nfin\n"));
+    }
+}
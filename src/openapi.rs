@@ -0,0 +1,133 @@
+use crate::registry::{ParamKind, ParamSpec, Registry, RouteSpec};
+use serde_json::{json, Map, Value};
+
+/// Esquema JSON de un parámetro a partir de su tipo, rango y default
+fn param_schema(p: &ParamSpec) -> Value {
+    let mut schema = Map::new();
+    match p.kind {
+        ParamKind::Int => {
+            schema.insert("type".into(), json!("integer"));
+            schema.insert("format".into(), json!("int64"));
+        }
+        ParamKind::UInt => {
+            schema.insert("type".into(), json!("integer"));
+            schema.insert("format".into(), json!("int64"));
+            schema.insert("minimum".into(), json!(p.min.unwrap_or(0)));
+        }
+        ParamKind::Text => {
+            schema.insert("type".into(), json!("string"));
+        }
+        ParamKind::Float => {
+            schema.insert("type".into(), json!("number"));
+            schema.insert("format".into(), json!("double"));
+        }
+        ParamKind::Duration => {
+            schema.insert("type".into(), json!("string"));
+            schema.insert("pattern".into(), json!(r"^[0-9]+(\.[0-9]+)?(ms|s|m)?$"));
+        }
+    }
+    if let Some(min) = p.min {
+        schema.insert("minimum".into(), json!(min));
+    }
+    if let Some(max) = p.max {
+        schema.insert("maximum".into(), json!(max));
+    }
+    if !p.choices.is_empty() {
+        schema.insert("enum".into(), json!(p.choices));
+    }
+    if let Some(d) = p.default {
+        // El default se guarda como texto; se publica con el tipo del parámetro
+        let value = match p.kind {
+            ParamKind::Text | ParamKind::Duration => json!(d),
+            ParamKind::Float => d.parse::<f64>().map(|n| json!(n)).unwrap_or_else(|_| json!(d)),
+            _ => d.parse::<i64>().map(|n| json!(n)).unwrap_or_else(|_| json!(d)),
+        };
+        schema.insert("default".into(), value);
+    }
+    Value::Object(schema)
+}
+
+/// Operación OpenAPI de una ruta para un método
+fn operation(r: &RouteSpec, method: &str) -> Value {
+    let params: Vec<Value> = r
+        .params
+        .iter()
+        .map(|p| {
+            json!({
+                "name": p.name,
+                "in": "query",
+                "required": p.required,
+                "description": p.description,
+                "schema": param_schema(p),
+            })
+        })
+        .collect();
+
+    let id = r.path.trim_start_matches('/');
+    let mut op = json!({
+        "summary": r.description,
+        "operationId": if method == "GET" { id.to_string() } else { format!("{}_{}", id, method.to_lowercase()) },
+        "parameters": params,
+        "responses": {
+            "200": { "description": "OK" },
+            "400": { "description": "Parámetros inválidos" },
+            "405": { "description": "Método no permitido" },
+        },
+    });
+    if method == "POST" {
+        op["requestBody"] = json!({
+            "required": false,
+            "content": { "text/plain": { "schema": { "type": "string" } } },
+        });
+    }
+    op
+}
+
+/// Documento OpenAPI 3 generado desde la tabla de rutas
+pub fn document(registry: &Registry) -> Value {
+    let mut paths = Map::new();
+    for r in registry.specs() {
+        let mut item = Map::new();
+        for m in r.methods {
+            item.insert(m.to_lowercase(), operation(r, m));
+        }
+        paths.insert(r.path.to_string(), Value::Object(item));
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::builtin_registry;
+
+    #[test]
+    fn document_includes_param_bounds() {
+        let doc = document(&builtin_registry());
+        assert_eq!(doc["openapi"], "3.0.3");
+        let count = &doc["paths"]["/random"]["get"]["parameters"][0];
+        assert_eq!(count["name"], "count");
+        assert_eq!(count["required"], true);
+        assert_eq!(count["schema"]["minimum"], 1);
+        let repeat = &doc["paths"]["/createfile"]["get"]["parameters"][2];
+        assert_eq!(repeat["schema"]["default"], 1);
+    }
+
+    #[test]
+    fn post_operations_have_body_and_unique_ids() {
+        let doc = document(&builtin_registry());
+        let reverse = &doc["paths"]["/reverse"];
+        assert_eq!(reverse["get"]["operationId"], "reverse");
+        assert_eq!(reverse["post"]["operationId"], "reverse_post");
+        assert!(reverse["post"]["requestBody"]["content"]["text/plain"].is_object());
+        assert!(reverse["get"].get("requestBody").is_none());
+    }
+}
@@ -0,0 +1,724 @@
+use crate::cputime::{cpu_since, thread_cpu_time};
+use crate::disconnect::client_gone;
+use crate::frontend::ConnSlot;
+use crate::http::{Cancel, Request};
+use crate::registry::{ParamKind, ParamSpec, Params, PoolSpec};
+use crate::scheduler::{PolicyKind, Scheduler, SchedulerInfo, SchedulerSettings};
+use crate::server::respond_slice;
+use crate::state::{SharedState, WorkerInfo};
+use crate::trace::Phase;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Petición aceptada esperando un worker de su pool
+pub struct Task {
+    pub request: Request,
+    pub stream: TcpStream,
+    /// Identificador de la tarea en la traza
+    pub trace_id: u64,
+    /// Lugar de la conexión entre las abiertas; se libera con la tarea
+    pub conn: ConnSlot,
+}
+
+/// Nombre del pool compartido, que atiende las rutas sin pool propio
+pub const SHARED_POOL: &str = "*";
+
+/// Cada cuánto revisa un worker ocioso si le toca retirarse
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// Cada cuánto mide el auto-escalador la presión de las colas
+pub const SCALE_TICK: Duration = Duration::from_millis(100);
+
+/// Cada cuánto se revisa si los clientes de las tareas siguen conectados
+pub const DISCONNECT_POLL: Duration = Duration::from_millis(50);
+
+/// Eventos de escalado que se guardan para /status
+pub const MAX_SCALING_EVENTS: usize = 50;
+
+/// Umbrales del auto-escalado de los pools
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScalingSettings {
+    /// Máximo de workers sumando todos los pools
+    pub thread_budget: usize,
+    /// Crece si hay más de `queue_depth` peticiones en cola por worker...
+    pub queue_depth: usize,
+    /// ...o si la más antigua lleva esperando más que esto
+    pub queue_wait: Duration,
+    /// Se achica tras estar ocioso (sin cola ni workers ocupados) este tiempo
+    pub idle: Duration,
+}
+
+impl Default for ScalingSettings {
+    fn default() -> Self {
+        ScalingSettings {
+            thread_budget: 256,
+            queue_depth: 4,
+            queue_wait: Duration::from_millis(100),
+            idle: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Cambio de tamaño de un pool, para los logs y /status
+#[derive(Clone, Debug, Serialize)]
+pub struct ScalingEvent {
+    pub at: DateTime<Utc>,
+    pub pool: String,
+    pub from: usize,
+    pub to: usize,
+    pub reason: String,
+}
+
+/// Carga de un pool en un instante
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Load {
+    pub size: usize,
+    pub busy: usize,
+    pub queued: usize,
+    pub oldest_wait: Option<Duration>,
+    pub idle_for: Duration,
+}
+
+/// Decisión del auto-escalador para un pool
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scale {
+    Up(String),
+    Down(String),
+}
+
+/// Decide si un pool crece o se achica un worker
+///   - `spare`: hilos que quedan libres en el presupuesto global
+pub fn scale_decision(load: Load, min: usize, max: usize, settings: &ScalingSettings, spare: usize) -> Option<Scale> {
+    if load.size < max && spare > 0 {
+        if load.queued > settings.queue_depth * load.size.max(1) {
+            return Some(Scale::Up(format!("{} peticiones en cola", load.queued)));
+        }
+        if let Some(wait) = load.oldest_wait.filter(|w| *w >= settings.queue_wait) {
+            return Some(Scale::Up(format!("espera de {} ms en cola", wait.as_millis())));
+        }
+    }
+    if load.size > min && load.queued == 0 && load.busy == 0 && load.idle_for >= settings.idle {
+        return Some(Scale::Down(format!("ocioso por {} s", load.idle_for.as_secs())));
+    }
+    None
+}
+
+/// Qué pasa con las peticiones nuevas mientras un pool está en pausa
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseMode {
+    /// Se encolan y esperan a que se reanude
+    Queue,
+    /// Se rechazan con 503 (las ya encoladas siguen esperando)
+    Reject,
+}
+
+/// Estado de un pool para /status y /admin/pools
+#[derive(Clone, Debug, Serialize)]
+pub struct PoolStatus {
+    pub pool: String,
+    pub policy: &'static str,
+    pub size: usize,
+    pub min: usize,
+    pub max: usize,
+    pub busy: usize,
+    pub queued: usize,
+    pub paused: Option<PauseMode>,
+    /// Fracción del tiempo de sus workers que pasaron atendiendo peticiones
+    pub utilization: f64,
+    /// Peticiones que este pool, saturado, mandó al compartido
+    pub borrowed: usize,
+    /// Peticiones de otros pools que atendió (solo el compartido)
+    pub lent: usize,
+    /// Tareas descartadas porque el cliente se desconectó
+    pub abandoned: usize,
+}
+
+struct Sizing {
+    /// Hilos vivos (incluye los que ya deben retirarse)
+    live: usize,
+    /// Hilos marcados para salir cuando terminen de esperar trabajo
+    retiring: usize,
+    busy: usize,
+    last_active: Instant,
+    min: usize,
+    max: usize,
+    paused: Option<PauseMode>,
+    /// Tiempo de workers ocupados y tiempo de workers vivos (integral de
+    /// `live`, al día hasta `since`), para la utilización
+    busy_time: Duration,
+    capacity: Duration,
+    since: Instant,
+    borrowed: usize,
+    lent: usize,
+    abandoned: usize,
+}
+
+impl Sizing {
+    /// Suma a `capacity` el tiempo de los hilos vivos desde la última vez
+    fn advance(&mut self) {
+        let now = Instant::now();
+        self.capacity += now.duration_since(self.since) * self.live as u32;
+        self.since = now;
+    }
+}
+
+/// Pool de workers de una ruta: su cola (con la política activa) y el
+/// tamaño, que el auto-escalador ajusta entre `min` y `max`
+pub struct Pool {
+    pub name: String,
+    /// Si con el pool saturado las peticiones pasan al compartido
+    pub borrow: bool,
+    pub scheduler: Scheduler<Task>,
+    sizing: Mutex<Sizing>,
+    /// Conexiones de las tareas en curso, para avisarles si el cliente se va
+    running: Mutex<Vec<(u64, TcpStream, Cancel)>>,
+}
+
+impl Pool {
+    /// Pool sin hilos; se arrancan con `spawn_worker`
+    pub fn new(name: &str, spec: PoolSpec, policy: PolicyKind, settings: SchedulerSettings) -> Arc<Pool> {
+        Arc::new(Pool {
+            name: name.to_string(),
+            borrow: spec.borrow,
+            scheduler: Scheduler::new(name, policy, settings),
+            sizing: Mutex::new(Sizing {
+                live: 0,
+                retiring: 0,
+                busy: 0,
+                last_active: Instant::now(),
+                min: spec.min.max(1),
+                max: spec.max.max(spec.min).max(1),
+                paused: None,
+                busy_time: Duration::ZERO,
+                capacity: Duration::ZERO,
+                since: Instant::now(),
+                borrowed: 0,
+                lent: 0,
+                abandoned: 0,
+            }),
+            running: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Workers activos (sin contar los que se están retirando)
+    pub fn size(&self) -> usize {
+        let s = self.sizing.lock().unwrap();
+        s.live - s.retiring
+    }
+
+    pub fn limits(&self) -> (usize, usize) {
+        let s = self.sizing.lock().unwrap();
+        (s.min, s.max)
+    }
+
+    pub fn load(&self) -> Load {
+        let s = self.sizing.lock().unwrap();
+        Load {
+            size: s.live - s.retiring,
+            busy: s.busy,
+            queued: self.scheduler.queued(),
+            oldest_wait: self.scheduler.oldest_wait(),
+            idle_for: s.last_active.elapsed(),
+        }
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        let load = self.load();
+        let mut s = self.sizing.lock().unwrap();
+        s.advance();
+        let capacity = s.capacity.as_secs_f64();
+        PoolStatus {
+            pool: self.name.clone(),
+            policy: self.scheduler.policy().name(),
+            size: load.size,
+            min: s.min,
+            max: s.max,
+            busy: load.busy,
+            queued: load.queued,
+            paused: s.paused,
+            utilization: if capacity > 0.0 { s.busy_time.as_secs_f64() / capacity } else { 0.0 },
+            borrowed: s.borrowed,
+            lent: s.lent,
+            abandoned: s.abandoned,
+        }
+    }
+
+    /// Todos los workers ocupados: una petición nueva tendría que esperar
+    pub fn saturated(&self) -> bool {
+        let s = self.sizing.lock().unwrap();
+        s.busy >= s.live - s.retiring
+    }
+
+    /// Cuenta una petición que este pool pasó a `shared` por estar saturado
+    pub fn lend_to(&self, shared: &Pool) {
+        self.sizing.lock().unwrap().borrowed += 1;
+        shared.sizing.lock().unwrap().lent += 1;
+    }
+
+    /// Pausa (o reanuda con `None`) la atención de la cola
+    pub fn set_paused(&self, mode: Option<PauseMode>) {
+        self.sizing.lock().unwrap().paused = mode;
+        self.scheduler.hold(mode.is_some());
+    }
+
+    /// false si el pool está en pausa y rechaza peticiones nuevas
+    pub fn admits(&self) -> bool {
+        self.sizing.lock().unwrap().paused != Some(PauseMode::Reject)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.sizing.lock().unwrap().paused.is_some()
+    }
+
+    /// Cambia los límites del auto-escalado y lleva el pool a `size` workers
+    /// (si se indica), arrancando o retirando los que falten o sobren. Los
+    /// límites se amplían para que el tamaño pedido quede dentro
+    pub fn resize(self: &Arc<Self>, state: &SharedState, size: Option<usize>, min: Option<usize>, max: Option<usize>) {
+        let current = {
+            let mut s = self.sizing.lock().unwrap();
+            s.min = min.unwrap_or(s.min).max(1);
+            s.max = max.unwrap_or(s.max).max(s.min);
+            if let Some(n) = size {
+                s.min = s.min.min(n);
+                s.max = s.max.max(n);
+                // Antes de arrancar hilos nuevos se perdona a los que se retiraban
+                let rescued = s.retiring.min(n.saturating_sub(s.live - s.retiring));
+                s.retiring -= rescued;
+            }
+            s.live - s.retiring
+        };
+        let Some(target) = size else { return };
+        for _ in current..target {
+            self.spawn_worker(state);
+        }
+        for _ in target..current {
+            self.retire_one();
+        }
+    }
+
+    /// Retira un worker cuando termine lo que está haciendo (baja el mínimo
+    /// si hace falta); nunca el último
+    pub fn drain_one(&self) -> bool {
+        let mut s = self.sizing.lock().unwrap();
+        let size = s.live - s.retiring;
+        if size <= 1 {
+            return false;
+        }
+        s.min = s.min.min(size - 1);
+        s.retiring += 1;
+        true
+    }
+
+    /// Arranca un worker más
+    pub fn spawn_worker(self: &Arc<Self>, state: &SharedState) {
+        {
+            let mut s = self.sizing.lock().unwrap();
+            s.advance();
+            s.live += 1;
+        }
+        let pool = self.clone();
+        let state = state.clone();
+        thread::spawn(move || pool.worker_loop(state));
+    }
+
+    /// Marca un worker para retirarse en cuanto esté ocioso
+    ///   - false si el pool ya está en su mínimo
+    pub fn retire_one(&self) -> bool {
+        let mut s = self.sizing.lock().unwrap();
+        if s.live - s.retiring <= s.min {
+            return false;
+        }
+        s.retiring += 1;
+        true
+    }
+
+    /// Lo llama un worker ocioso: true si debe terminar
+    fn should_exit(&self) -> bool {
+        let mut s = self.sizing.lock().unwrap();
+        if s.retiring == 0 {
+            return false;
+        }
+        s.retiring -= 1;
+        s.advance();
+        s.live -= 1;
+        true
+    }
+
+    fn start_job(&self) {
+        let mut s = self.sizing.lock().unwrap();
+        s.busy += 1;
+        s.last_active = Instant::now();
+    }
+
+    fn finish_job(&self, took: Duration) {
+        let mut s = self.sizing.lock().unwrap();
+        s.busy -= 1;
+        s.busy_time += took;
+        s.last_active = Instant::now();
+    }
+
+    /// Cuenta una tarea que el cliente abandonó (la conexión se cierra al soltarla)
+    fn abandoned(&self, state: &SharedState, task: &Task, worker: Option<&str>, path: &str) {
+        self.sizing.lock().unwrap().abandoned += 1;
+        let mut st = state.lock().unwrap();
+        st.client_abandoned += 1;
+        st.trace.abandon(task.trace_id, &self.name, worker, path);
+    }
+
+    /// Saca de la cola las tareas cuyo cliente se fue y avisa a los handlers
+    /// en curso cuyo cliente se fue
+    pub fn reap_abandoned(&self, state: &SharedState) {
+        for job in self.scheduler.cancel_where(|task| client_gone(&task.stream)) {
+            self.abandoned(state, &job.payload, None, &job.payload.request.path_and_query());
+        }
+        for (_, stream, cancel) in self.running.lock().unwrap().iter() {
+            if !cancel.is_cancelled() && client_gone(stream) {
+                cancel.cancel();
+            }
+        }
+    }
+
+    fn worker_loop(self: Arc<Self>, state: SharedState) {
+        // 1) Registrar este worker
+        let worker = format!("{:?}", thread::current().id());
+        state.lock().unwrap().workers.push(WorkerInfo {
+            command: self.name.clone(),
+            thread_id: worker.clone(),
+            busy: false,
+            cpu_time_ms: 0.0,
+        });
+
+        loop {
+            let Some(mut job) = self.scheduler.next_timeout(IDLE_POLL) else {
+                if self.should_exit() {
+                    break;
+                }
+                continue;
+            };
+            let trace_path = job.payload.request.path_and_query();
+            // El cliente pudo irse mientras esperaba (o en una porción anterior)
+            if job.payload.request.cancel.is_cancelled() || client_gone(&job.payload.stream) {
+                self.abandoned(&state, &job.payload, Some(&worker), &trace_path);
+                continue;
+            }
+            // Marcar busy = true
+            self.start_job();
+            {
+                let mut st = state.lock().unwrap();
+                if let Some(w) = st.workers.iter_mut().find(|w| w.thread_id == worker) {
+                    w.busy = true;
+                }
+                st.trace.record(Phase::Start, job.payload.trace_id, &self.name, &worker, &trace_path);
+            }
+            // Procesar una porción; si el handler cede, vuelve a la cola
+            let started = Instant::now();
+            let cpu_start = thread_cpu_time();
+            let slice = self.scheduler.quantum().unwrap_or(Duration::MAX);
+            let Task { request, stream, trace_id, .. } = &mut job.payload;
+            if let Ok(watch) = stream.try_clone() {
+                self.running.lock().unwrap().push((*trace_id, watch, request.cancel.clone()));
+            }
+            let finished = respond_slice(stream, request, &state, slice);
+            self.running.lock().unwrap().retain(|(id, _, _)| id != trace_id);
+            let cpu = cpu_since(cpu_start);
+
+            // Marcar busy = false y acumular CPU
+            self.finish_job(started.elapsed());
+            {
+                let mut st = state.lock().unwrap();
+                if let Some(w) = st.workers.iter_mut().find(|w| w.thread_id == worker) {
+                    w.busy = false;
+                    w.cpu_time_ms += cpu.as_secs_f64() * 1000.0;
+                }
+                let phase = if finished { Phase::Finish } else { Phase::Yield };
+                if !job.payload.request.cancel.is_cancelled() {
+                    st.trace.record(phase, job.payload.trace_id, &self.name, &worker, &trace_path);
+                }
+            }
+            if job.payload.request.cancel.is_cancelled() {
+                self.abandoned(&state, &job.payload, Some(&worker), &trace_path);
+            } else if finished {
+                self.scheduler.complete(&job);
+            } else {
+                self.scheduler.requeue(job);
+            }
+            // Un pool siempre ocupado también debe poder achicarse
+            if self.should_exit() {
+                break;
+            }
+        }
+
+        state.lock().unwrap().workers.retain(|w| w.thread_id != worker);
+    }
+}
+
+/// Pool que atiende una petición: el propio de la ruta o, si no tiene, el
+/// compartido. Un pool propio saturado que admite préstamo manda la
+/// petición al compartido cuando este tiene workers libres. Solo elige: el
+/// préstamo se cuenta con `lend_to` una vez encolada la petición
+pub fn choose_pool<'a>(dedicated: Option<&'a Arc<Pool>>, shared: &'a Arc<Pool>) -> &'a Arc<Pool> {
+    match dedicated {
+        Some(pool) if pool.borrow && pool.saturated() && !shared.saturated() && shared.admits() => shared,
+        Some(pool) => pool,
+        None => shared,
+    }
+}
+
+/// Parámetros de /admin/pools
+pub const POOLS_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("action", ParamKind::Text, "show", "show, resize, pause, resume, drain (retira un worker) o policy")
+        .one_of(&["show", "resize", "pause", "resume", "drain", "policy"]),
+    ParamSpec::optional_no_default("route", ParamKind::Text, "ruta del pool (requerida salvo en show)"),
+    ParamSpec::optional_no_default("size", ParamKind::UInt, "workers a dejar en el pool (resize)").range(Some(1), Some(1024)),
+    ParamSpec::optional_no_default("min", ParamKind::UInt, "mínimo del auto-escalado (resize)").range(Some(1), Some(1024)),
+    ParamSpec::optional_no_default("max", ParamKind::UInt, "máximo del auto-escalado (resize)").range(Some(1), Some(1024)),
+    ParamSpec::optional("mode", ParamKind::Text, "queue", "en pausa: encolar o rechazar con 503 (pause)").one_of(&["queue", "reject"]),
+    ParamSpec::optional_no_default("policy", ParamKind::Text, "política de la cola (policy)")
+        .one_of(&["fifo", "sjf", "priority", "rr"]),
+];
+
+/// Consulta o cambia los pools mientras el servidor corre; siempre responde
+/// con el estado resultante de todos los pools
+pub fn handle_pools(params: &Params, state: &SharedState) -> String {
+    let (pools, budget) = {
+        let st = state.lock().unwrap();
+        (st.pools.clone(), st.config.scaling.thread_budget)
+    };
+    let bad = |msg: String| format!("HTTP/1.0 400 Bad Request\r\n\r\n{}\n", msg);
+    let action = params.str("action").unwrap_or("show");
+
+    if action != "show" {
+        let Some(route) = params.str("route") else {
+            return bad(format!("Parámetro 'route' requerido para action={}", action));
+        };
+        let Some(pool) = pools.iter().find(|p| p.name == route) else {
+            return bad(format!("No hay pool para '{}'", route));
+        };
+        let admin = route.starts_with("/admin/");
+        match action {
+            "resize" => {
+                let size = params.usize("size");
+                let others: usize = pools.iter().filter(|p| p.name != route).map(|p| p.size()).sum();
+                if let Some(n) = size.filter(|n| others + n > budget) {
+                    return bad(format!("{} workers excederían el presupuesto de {} hilos", n, budget));
+                }
+                pool.resize(state, size, params.usize("min"), params.usize("max"));
+            }
+            "pause" if admin => return bad("Los pools de administración no se pueden pausar".to_string()),
+            "pause" => {
+                let mode = if params.str("mode") == Some("reject") { PauseMode::Reject } else { PauseMode::Queue };
+                pool.set_paused(Some(mode));
+            }
+            "resume" => pool.set_paused(None),
+            "drain" if !pool.drain_one() => {
+                return bad(format!("El pool '{}' tiene un solo worker; use action=pause", route));
+            }
+            "policy" => match params.str("policy").and_then(PolicyKind::parse) {
+                Some(kind) => pool.scheduler.set_policy(kind),
+                None => return bad("Parámetro 'policy' requerido para action=policy".to_string()),
+            },
+            _ => {}
+        }
+        println!("Admin: {} en el pool {}", action, route);
+    }
+
+    let body: Vec<PoolStatus> = pools.iter().map(|p| p.status()).collect();
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        serde_json::to_string(&body).unwrap()
+    )
+}
+
+/// Una pasada del auto-escalador: cada pool crece o se achica a lo sumo un
+/// worker, sin pasar del presupuesto global de hilos
+pub fn autoscale(pools: &[Arc<Pool>], state: &SharedState, settings: &ScalingSettings) {
+    let mut threads: usize = pools.iter().map(|p| p.size()).sum();
+    for pool in pools {
+        // Un pool en pausa acumula cola a propósito: no se escala
+        if pool.is_paused() {
+            continue;
+        }
+        let load = pool.load();
+        let (min, max) = pool.limits();
+        let spare = settings.thread_budget.saturating_sub(threads);
+        let (to, reason) = match scale_decision(load, min, max, settings, spare) {
+            Some(Scale::Up(reason)) => {
+                pool.spawn_worker(state);
+                threads += 1;
+                (load.size + 1, reason)
+            }
+            Some(Scale::Down(reason)) if pool.retire_one() => {
+                threads -= 1;
+                (load.size - 1, reason)
+            }
+            _ => continue,
+        };
+        println!("Pool {}: {} -> {} workers ({})", pool.name, load.size, to, reason);
+        let mut st = state.lock().unwrap();
+        if st.scaling_events.len() == MAX_SCALING_EVENTS {
+            st.scaling_events.pop_front();
+        }
+        st.scaling_events.push_back(ScalingEvent {
+            at: Utc::now(),
+            pool: pool.name.clone(),
+            from: load.size,
+            to,
+            reason,
+        });
+    }
+}
+
+/// Hilo que cada `DISCONNECT_POLL` busca clientes que abandonaron sus
+/// peticiones, en cola o en curso
+pub fn spawn_disconnect_watcher(pools: Vec<Arc<Pool>>, state: SharedState) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(DISCONNECT_POLL);
+            for pool in &pools {
+                pool.reap_abandoned(&state);
+            }
+        }
+    });
+}
+
+/// Hilo que corre `autoscale` cada `SCALE_TICK`
+pub fn spawn_autoscaler(pools: Vec<Arc<Pool>>, state: SharedState, settings: ScalingSettings) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(SCALE_TICK);
+            autoscale(&pools, &state, &settings);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> ScalingSettings {
+        ScalingSettings { thread_budget: 10, queue_depth: 2, queue_wait: Duration::from_millis(50), idle: Duration::from_secs(1) }
+    }
+
+    #[test]
+    fn grows_on_queue_depth_or_wait() {
+        let s = settings();
+        let busy = Load { size: 2, busy: 2, ..Default::default() };
+        assert_eq!(scale_decision(busy, 1, 4, &s, 5), None);
+        let deep = Load { queued: 5, ..busy };
+        assert!(matches!(scale_decision(deep, 1, 4, &s, 5), Some(Scale::Up(_))));
+        let slow = Load { queued: 1, oldest_wait: Some(Duration::from_millis(80)), ..busy };
+        assert!(matches!(scale_decision(slow, 1, 4, &s, 5), Some(Scale::Up(_))));
+        // Ni por encima del máximo ni del presupuesto global
+        assert_eq!(scale_decision(Load { size: 4, ..deep }, 1, 4, &s, 5), None);
+        assert_eq!(scale_decision(deep, 1, 4, &s, 0), None);
+    }
+
+    #[test]
+    fn shrinks_only_when_idle_long_enough() {
+        let s = settings();
+        let idle = Load { size: 3, idle_for: Duration::from_secs(2), ..Default::default() };
+        assert!(matches!(scale_decision(idle, 1, 4, &s, 5), Some(Scale::Down(_))));
+        assert_eq!(scale_decision(Load { busy: 1, ..idle }, 1, 4, &s, 5), None);
+        assert_eq!(scale_decision(Load { idle_for: Duration::from_millis(10), ..idle }, 1, 4, &s, 5), None);
+        assert_eq!(scale_decision(Load { size: 1, ..idle }, 1, 4, &s, 5), None);
+    }
+
+    fn wait_for(cond: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !cond() {
+            assert!(start.elapsed() < Duration::from_secs(5), "la condición no se cumplió a tiempo");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn admin_resizes_pauses_and_switches_policy() {
+        let state = crate::state::new_state();
+        let spec = PoolSpec { max: 4, ..PoolSpec::sized(2) };
+        let pool = Pool::new("/x", spec, PolicyKind::Fifo, SchedulerSettings::default());
+        state.lock().unwrap().pools.push(pool.clone());
+        let admin = |query: &str| {
+            let params = crate::registry::validate(POOLS_PARAMS, query).unwrap();
+            handle_pools(&params, &state)
+        };
+        let workers = || state.lock().unwrap().workers.iter().filter(|w| w.command == "/x").count();
+
+        let resp = admin("action=resize&route=/x&size=3&max=6");
+        assert!(resp.contains("\"size\":3"), "{}", resp);
+        assert_eq!(pool.limits(), (1, 6));
+        wait_for(|| workers() == 3);
+
+        admin("action=drain&route=/x");
+        wait_for(|| workers() == 2);
+        admin("action=resize&route=/x&size=1");
+        wait_for(|| workers() == 1);
+        assert!(admin("action=drain&route=/x").starts_with("HTTP/1.0 400"));
+
+        admin("action=pause&route=/x&mode=reject");
+        assert!(!pool.admits());
+        assert!(admin("action=show").contains("\"paused\":\"reject\""));
+        admin("action=resume&route=/x");
+        assert!(pool.admits());
+
+        admin("action=policy&route=/x&policy=sjf");
+        assert_eq!(pool.scheduler.policy(), PolicyKind::Sjf);
+
+        assert!(admin("action=pause&route=/nope").starts_with("HTTP/1.0 400"));
+        assert!(admin("action=resize&route=/x&size=1000").starts_with("HTTP/1.0 400"));
+    }
+
+    #[test]
+    fn saturated_pool_borrows_from_shared() {
+        let settings = SchedulerSettings::default();
+        let shared = Pool::new(SHARED_POOL, PoolSpec::sized(2), PolicyKind::Fifo, settings);
+        let hot = Pool::new("/hot", PoolSpec { borrow: true, ..PoolSpec::sized(1) }, PolicyKind::Fifo, settings);
+        let own = Pool::new("/own", PoolSpec::sized(1), PolicyKind::Fifo, settings);
+        // Hilos "vivos" sin arrancarlos: alcanza para las cuentas
+        shared.sizing.lock().unwrap().live = 2;
+        hot.sizing.lock().unwrap().live = 1;
+        own.sizing.lock().unwrap().live = 1;
+
+        assert_eq!(choose_pool(None, &shared).name, SHARED_POOL);
+        assert_eq!(choose_pool(Some(&hot), &shared).name, "/hot");
+
+        // Con todos sus workers ocupados, /hot presta del compartido y /own no
+        hot.start_job();
+        own.start_job();
+        assert_eq!(choose_pool(Some(&hot), &shared).name, SHARED_POOL);
+        assert_eq!(choose_pool(Some(&own), &shared).name, "/own");
+        // Elegir no cuenta el préstamo: lo cuenta quien encola
+        assert_eq!((hot.status().borrowed, shared.status().lent), (0, 0));
+        hot.lend_to(&shared);
+        assert_eq!((hot.status().borrowed, shared.status().lent), (1, 1));
+
+        // Si el compartido también está lleno, se queda en la cola propia
+        shared.start_job();
+        shared.start_job();
+        assert_eq!(choose_pool(Some(&hot), &shared).name, "/hot");
+
+        thread::sleep(Duration::from_millis(10));
+        hot.finish_job(Duration::from_millis(5));
+        let utilization = hot.status().utilization;
+        assert!(utilization > 0.0 && utilization <= 1.0, "{}", utilization);
+    }
+
+    #[test]
+    fn retire_respects_minimum() {
+        let spec = PoolSpec { workers: 3, min: 2, max: 4, ..PoolSpec::sized(3) };
+        let pool = Pool::new("/x", spec, PolicyKind::Fifo, SchedulerSettings::default());
+        pool.sizing.lock().unwrap().live = 3;
+        assert!(pool.retire_one());
+        assert!(!pool.retire_one());
+        assert_eq!(pool.size(), 2);
+        assert!(pool.should_exit());
+        assert!(!pool.should_exit());
+        assert_eq!(pool.size(), 2);
+    }
+}
@@ -0,0 +1,444 @@
+use crate::http::ResponseSink;
+use crate::registry::Params;
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::io::{self, BufWriter, Write};
+
+/// A partir de este `count` la respuesta se envía por partes, sin armarla en memoria
+pub const STREAM_THRESHOLD: usize = 10_000;
+
+/// Máximo de valores por petición
+pub const MAX_COUNT: i64 = 10_000_000;
+
+/// Distribución de los números generados
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dist {
+    /// Uniforme en `[min, max]`
+    Uniform { min: i64, max: i64 },
+    Normal { mean: f64, stddev: f64 },
+    Exponential { lambda: f64 },
+    Poisson { lambda: f64 },
+}
+
+/// Qué se genera en cada posición
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    Int(Dist),
+    Float(Dist),
+    /// Cadenas de `length` caracteres tomados de `charset`
+    Text { length: usize, charset: &'static [u8] },
+    /// UUID versión 4
+    Uuid,
+    /// Elementos tomados al azar de la lista
+    Choice(Vec<String>),
+    /// Permutación aleatoria de la lista (los primeros `count` elementos)
+    Shuffle(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+    Lines,
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Lines => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// Petición a /random ya validada
+#[derive(Clone, Debug, PartialEq)]
+pub struct RandomSpec {
+    pub count: usize,
+    pub kind: Kind,
+    /// Sin repetición (solo enteros uniformes y `choice`)
+    pub unique: bool,
+    pub format: Format,
+}
+
+impl RandomSpec {
+    /// `count` enteros uniformes en `[min, max]` en JSON (el /random original)
+    pub fn ints(count: usize, min: i64, max: i64) -> Self {
+        RandomSpec {
+            count,
+            kind: Kind::Int(Dist::Uniform { min, max }),
+            unique: false,
+            format: Format::Json,
+        }
+    }
+}
+
+const ALNUM: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+fn charset(name: &str) -> &'static [u8] {
+    match name {
+        "alpha" => &ALNUM[..52],
+        "hex" => b"0123456789abcdef",
+        "digits" => &ALNUM[52..],
+        _ => ALNUM,
+    }
+}
+
+/// Arma la especificación a partir de los parámetros validados de /random
+///   - `min` y `max` son obligatorios con `dist=uniform` en enteros y flotantes
+///   - `items` es obligatorio con `type=choice` y `type=shuffle`
+///   - `unique` solo con enteros uniformes o `choice`, y `count` no puede
+///     superar la cantidad de valores distintos posibles
+pub fn random_spec(params: &Params) -> Result<RandomSpec, String> {
+    let count = params.usize("count").unwrap_or(1);
+    let unique = params.str("unique") == Some("true");
+    let kind_name = params.str("type").unwrap_or("int");
+    let dist_name = params.str("dist").unwrap_or("uniform");
+
+    if dist_name != "uniform" && !matches!(kind_name, "int" | "float") {
+        return Err("Parámetro 'dist' solo se admite con type=int o type=float".to_string());
+    }
+    let lambda = || match params.f64("lambda").unwrap_or(1.0) {
+        l if l > 0.0 => Ok(l),
+        _ => Err("Parámetro 'lambda' debe ser mayor que cero".to_string()),
+    };
+    let dist = || -> Result<Dist, String> {
+        Ok(match dist_name {
+            "normal" => Dist::Normal {
+                mean: params.f64("mean").unwrap_or(0.0),
+                stddev: params.f64("stddev").unwrap_or(1.0),
+            },
+            "exponential" => Dist::Exponential { lambda: lambda()? },
+            "poisson" => Dist::Poisson { lambda: lambda()? },
+            _ => {
+                let (_, min, max) = crate::handlers::random_args(params)?;
+                Dist::Uniform { min, max }
+            }
+        })
+    };
+    let items = || -> Result<Vec<String>, String> {
+        let items: Vec<String> = params
+            .str("items")
+            .ok_or("Parámetro 'items' requerido")?
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if items.is_empty() {
+            return Err("Parámetro 'items' debe tener al menos un elemento".to_string());
+        }
+        Ok(items)
+    };
+
+    let kind = match kind_name {
+        "float" => Kind::Float(dist()?),
+        "string" => Kind::Text {
+            length: params.usize("length").unwrap_or(16),
+            charset: charset(params.str("charset").unwrap_or("alnum")),
+        },
+        "uuid" => Kind::Uuid,
+        "choice" => Kind::Choice(items()?),
+        "shuffle" => Kind::Shuffle(items()?),
+        _ => Kind::Int(dist()?),
+    };
+
+    // Cuántos valores distintos hay para los casos sin repetición
+    let distinct = match &kind {
+        Kind::Int(Dist::Uniform { min, max }) if unique => Some(i128::from(*max) - i128::from(*min) + 1),
+        Kind::Choice(items) if unique => Some(items.len() as i128),
+        Kind::Shuffle(items) => Some(items.len() as i128),
+        _ if unique => {
+            return Err(
+                "Parámetro 'unique' solo se admite con type=int (dist=uniform) o type=choice".to_string(),
+            );
+        }
+        _ => None,
+    };
+    if let Some(n) = distinct.filter(|&n| count as i128 > n) {
+        return Err(format!("Parámetro 'count' no puede superar los {} valores distintos posibles", n));
+    }
+
+    let format = match params.str("format") {
+        Some("csv") => Format::Csv,
+        Some("lines") => Format::Lines,
+        _ => Format::Json,
+    };
+    Ok(RandomSpec { count, kind, unique, format })
+}
+
+/// Un valor generado
+enum Sample {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// Normal estándar por Box-Muller
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.r#gen::<f64>();
+    let u2: f64 = rng.r#gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Poisson: algoritmo de Knuth para lambda chica, aproximación normal para grande
+fn poisson(rng: &mut StdRng, lambda: f64) -> f64 {
+    if lambda >= 30.0 {
+        return (lambda + lambda.sqrt() * standard_normal(rng)).round().max(0.0);
+    }
+    let limit = (-lambda).exp();
+    let mut k = 0.0;
+    let mut p: f64 = rng.r#gen();
+    while p > limit {
+        k += 1.0;
+        p *= rng.r#gen::<f64>();
+    }
+    k
+}
+
+fn sample_f64(rng: &mut StdRng, dist: Dist) -> f64 {
+    match dist {
+        Dist::Uniform { min, max } => rng.gen_range(min as f64..=max as f64),
+        Dist::Normal { mean, stddev } => mean + stddev * standard_normal(rng),
+        Dist::Exponential { lambda } => -(1.0 - rng.r#gen::<f64>()).ln() / lambda,
+        Dist::Poisson { lambda } => poisson(rng, lambda),
+    }
+}
+
+fn uuid_v4(rng: &mut StdRng) -> String {
+    let mut b: [u8; 16] = rng.r#gen();
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(b);
+    format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+}
+
+/// Índices distintos en `[0, n)`, en orden aleatorio
+fn distinct_indices(rng: &mut StdRng, n: u128, count: usize) -> Vec<u128> {
+    match usize::try_from(n) {
+        Ok(n) => index::sample(rng, n, count).into_iter().map(|i| i as u128).collect(),
+        // Rango más grande que usize: las repeticiones son casi imposibles
+        Err(_) => {
+            let mut seen = HashSet::with_capacity(count);
+            let mut out = Vec::with_capacity(count);
+            while out.len() < count {
+                let i = rng.gen_range(0..n);
+                if seen.insert(i) {
+                    out.push(i);
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Genera los valores de forma perezosa; solo los casos sin repetición
+/// preparan antes la lista de índices
+fn samples<'a>(spec: &'a RandomSpec, rng: &'a mut StdRng) -> Box<dyn Iterator<Item = Sample> + 'a> {
+    let count = spec.count;
+    match &spec.kind {
+        Kind::Int(Dist::Uniform { min, max }) if spec.unique => {
+            let n = (i128::from(*max) - i128::from(*min) + 1) as u128;
+            let min = *min;
+            let picks = distinct_indices(rng, n, count);
+            Box::new(picks.into_iter().map(move |i| Sample::Int((i128::from(min) + i as i128) as i64)))
+        }
+        Kind::Int(Dist::Uniform { min, max }) => {
+            let (min, max) = (*min, *max);
+            Box::new((0..count).map(move |_| Sample::Int(rng.gen_range(min..=max))))
+        }
+        Kind::Int(dist) => {
+            let dist = *dist;
+            Box::new((0..count).map(move |_| Sample::Int(sample_f64(rng, dist).round() as i64)))
+        }
+        Kind::Float(dist) => {
+            let dist = *dist;
+            Box::new((0..count).map(move |_| Sample::Float(sample_f64(rng, dist))))
+        }
+        Kind::Text { length, charset } => {
+            let (length, charset) = (*length, *charset);
+            Box::new((0..count).map(move |_| {
+                let s = (0..length).map(|_| charset[rng.gen_range(0..charset.len())] as char).collect();
+                Sample::Text(s)
+            }))
+        }
+        Kind::Uuid => Box::new((0..count).map(move |_| Sample::Text(uuid_v4(rng)))),
+        Kind::Choice(items) | Kind::Shuffle(items) if spec.unique || matches!(spec.kind, Kind::Shuffle(_)) => {
+            let picks = distinct_indices(rng, items.len() as u128, count);
+            Box::new(picks.into_iter().map(move |i| Sample::Text(items[i as usize].clone())))
+        }
+        Kind::Choice(items) | Kind::Shuffle(items) => {
+            Box::new((0..count).map(move |_| Sample::Text(items[rng.gen_range(0..items.len())].clone())))
+        }
+    }
+}
+
+fn write_value(out: &mut impl Write, sample: &Sample, format: Format) -> io::Result<()> {
+    match (sample, format) {
+        (Sample::Int(v), _) => write!(out, "{}", v),
+        (Sample::Float(v), _) => write!(out, "{}", serde_json::to_string(v).unwrap()),
+        (Sample::Text(s), Format::Json) => write!(out, "{}", serde_json::to_string(s).unwrap()),
+        (Sample::Text(s), Format::Csv) if s.contains([',', '"', '\n', '\r']) => {
+            write!(out, "\"{}\"", s.replace('"', "\"\""))
+        }
+        (Sample::Text(s), _) => out.write_all(s.as_bytes()),
+    }
+}
+
+/// Escribe el cuerpo: array JSON, CSV con cabecera `value` o un valor por línea
+fn write_body(spec: &RandomSpec, seed: u64, out: &mut impl Write) -> io::Result<()> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (open, sep, close) = match spec.format {
+        Format::Json => ("[", ",", "]\n"),
+        Format::Csv => ("value\n", "\n", "\n"),
+        Format::Lines => ("", "\n", "\n"),
+    };
+    out.write_all(open.as_bytes())?;
+    for (i, sample) in samples(spec, &mut rng).enumerate() {
+        if i > 0 {
+            out.write_all(sep.as_bytes())?;
+        }
+        write_value(out, &sample, spec.format)?;
+    }
+    out.write_all(close.as_bytes())
+}
+
+fn head(spec: &RandomSpec, seed: u64) -> String {
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: {}\r\n\
+         X-Random-Seed: {}\r\n\r\n",
+        spec.format.content_type(),
+        seed
+    )
+}
+
+/// Responde /random. La misma semilla da la misma secuencia; la semilla usada
+/// se informa en la cabecera `X-Random-Seed`. Si `count` supera
+/// `STREAM_THRESHOLD` y hay conexión, el cuerpo se escribe por partes en
+/// `sink` y se devuelve una respuesta vacía
+pub fn respond(spec: &RandomSpec, seed: u64, sink: Option<&ResponseSink>) -> String {
+    if let Some(sink) = sink.filter(|_| spec.count > STREAM_THRESHOLD) {
+        let mut out = BufWriter::with_capacity(64 * 1024, sink.clone());
+        // Si el cliente se va a mitad de camino no hay a quién avisar
+        let _ = out
+            .write_all(head(spec, seed).as_bytes())
+            .and_then(|_| write_body(spec, seed, &mut out))
+            .and_then(|_| out.flush());
+        return String::new();
+    }
+
+    let mut body = Vec::new();
+    write_body(spec, seed, &mut body).expect("escribir en memoria no falla");
+    head(spec, seed) + &String::from_utf8_lossy(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::RANDOM_PARAMS;
+    use crate::registry::validate;
+    use std::sync::{Arc, Mutex};
+
+    fn spec(query: &str) -> Result<RandomSpec, String> {
+        random_spec(&validate(RANDOM_PARAMS, query)?)
+    }
+
+    fn body(resp: &str) -> &str {
+        resp.split("\r\n\r\n").nth(1).unwrap()
+    }
+
+    #[test]
+    fn spec_errors() {
+        assert_eq!(spec("count=3").unwrap_err(), "Parámetro 'min' requerido");
+        assert_eq!(spec("count=3&type=choice").unwrap_err(), "Parámetro 'items' requerido");
+        assert_eq!(spec("count=3&type=uuid&dist=normal").unwrap_err(), "Parámetro 'dist' solo se admite con type=int o type=float");
+        assert_eq!(spec("count=3&dist=poisson&lambda=0").unwrap_err(), "Parámetro 'lambda' debe ser mayor que cero");
+        assert_eq!(spec("count=3&type=float&min=0&max=1&unique=true").unwrap_err(), "Parámetro 'unique' solo se admite con type=int (dist=uniform) o type=choice");
+        assert_eq!(spec("count=3&min=1&max=2&unique=true").unwrap_err(), "Parámetro 'count' no puede superar los 2 valores distintos posibles");
+        assert_eq!(spec("count=4&type=shuffle&items=a,b,c").unwrap_err(), "Parámetro 'count' no puede superar los 3 valores distintos posibles");
+    }
+
+    #[test]
+    fn unique_ints_cover_the_range() {
+        let resp = respond(&spec("count=10&min=1&max=10&unique=true").unwrap(), 5, None);
+        let mut v: Vec<i64> = serde_json::from_str(body(&resp)).unwrap();
+        v.sort();
+        assert_eq!(v, (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn distributions_have_the_expected_mean() {
+        let mean = |query: &str| {
+            let resp = respond(&spec(query).unwrap(), 11, None);
+            let v: Vec<f64> = serde_json::from_str(body(&resp)).unwrap();
+            v.iter().sum::<f64>() / v.len() as f64
+        };
+        assert!((mean("count=5000&type=float&dist=normal&mean=10&stddev=2") - 10.0).abs() < 0.2);
+        assert!((mean("count=5000&type=float&dist=exponential&lambda=4") - 0.25).abs() < 0.02);
+        assert!((mean("count=5000&dist=poisson&lambda=3") - 3.0).abs() < 0.15);
+        assert!((mean("count=5000&dist=poisson&lambda=100") - 100.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn strings_uuids_choices_and_shuffles() {
+        let resp = respond(&spec("count=3&type=string&length=8&charset=hex").unwrap(), 1, None);
+        let v: Vec<String> = serde_json::from_str(body(&resp)).unwrap();
+        assert!(v.iter().all(|s| s.len() == 8 && s.chars().all(|c| c.is_ascii_hexdigit())));
+
+        let resp = respond(&spec("count=2&type=uuid").unwrap(), 1, None);
+        let v: Vec<String> = serde_json::from_str(body(&resp)).unwrap();
+        assert!(v.iter().all(|u| u.len() == 36 && &u[14..15] == "4"));
+
+        let resp = respond(&spec("count=5&type=choice&items=x,y").unwrap(), 1, None);
+        let v: Vec<String> = serde_json::from_str(body(&resp)).unwrap();
+        assert!(v.iter().all(|s| s == "x" || s == "y"));
+
+        let resp = respond(&spec("count=3&type=shuffle&items=a,b,c").unwrap(), 1, None);
+        let mut v: Vec<String> = serde_json::from_str(body(&resp)).unwrap();
+        v.sort();
+        assert_eq!(v, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn csv_and_lines_formats() {
+        let resp = respond(&spec("count=2&min=7&max=7&format=csv").unwrap(), 1, None);
+        assert!(resp.contains("Content-Type: text/csv"));
+        assert_eq!(body(&resp), "value\n7\n7\n");
+
+        let resp = respond(&spec("count=2&type=choice&items=a\"b&format=csv").unwrap(), 1, None);
+        assert_eq!(body(&resp), "value\n\"a\"\"b\"\n\"a\"\"b\"\n");
+
+        let resp = respond(&spec("count=3&min=1&max=1&format=lines").unwrap(), 1, None);
+        assert_eq!(body(&resp), "1\n1\n1\n");
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn large_counts_are_streamed_to_the_sink() {
+        let buf = SharedBuf::default();
+        let sink = ResponseSink::new(buf.clone());
+        let spec = RandomSpec { format: Format::Lines, ..RandomSpec::ints(STREAM_THRESHOLD + 1, 0, 9) };
+
+        assert_eq!(respond(&spec, 3, Some(&sink)), "");
+        let streamed = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(streamed, respond(&spec, 3, None));
+        assert_eq!(body(&streamed).lines().count(), STREAM_THRESHOLD + 1);
+    }
+}
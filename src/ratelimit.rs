@@ -0,0 +1,256 @@
+use crate::http::{self, Request};
+use crate::middleware::{Middleware, Next};
+use crate::state::SharedState;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Límites para un cliente en una ruta
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    /// Tokens que se recuperan por segundo
+    pub per_second: f64,
+    /// Capacidad del bucket (ráfaga máxima)
+    pub burst: f64,
+    /// Peticiones simultáneas permitidas
+    pub max_concurrent: usize,
+}
+
+impl RateLimit {
+    pub const fn new(per_second: f64, burst: f64, max_concurrent: usize) -> Self {
+        RateLimit { per_second, burst, max_concurrent }
+    }
+}
+
+/// Estado de un cliente en una ruta: token bucket y peticiones en curso
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+    in_flight: usize,
+}
+
+/// A partir de cuántas entradas se limpian los buckets llenos e inactivos
+const PRUNE_THRESHOLD: usize = 10_000;
+
+type Buckets = Mutex<HashMap<(IpAddr, String), Bucket>>;
+
+/// Devuelve el hueco de concurrencia tomado por `RateLimiter::acquire`
+fn release(buckets: &Buckets, ip: IpAddr, path: &str) {
+    let mut buckets = buckets.lock().unwrap();
+    if let Some(b) = buckets.get_mut(&(ip, path.to_string())) {
+        b.in_flight = b.in_flight.saturating_sub(1);
+    }
+}
+
+/// Middleware de rate limiting por IP del cliente. Cada par (IP, ruta) tiene
+/// un token bucket y un tope de peticiones concurrentes; al pasarse se
+/// responde 429 con `Retry-After` y se cuenta en `ServerState.rate_limited`.
+/// Las peticiones sin IP conocida (p. ej. las de los tests) no se limitan,
+/// ni ninguna si el servidor arrancó con `--rate-limit=off`. Una petición
+/// que cede el hilo (scheduler round-robin) conserva su hueco de
+/// concurrencia hasta terminar
+pub struct RateLimiter {
+    default: RateLimit,
+    routes: HashMap<String, RateLimit>,
+    buckets: Arc<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(default: RateLimit) -> Self {
+        RateLimiter { default, routes: HashMap::new(), buckets: Arc::default() }
+    }
+
+    /// Límite propio para una ruta
+    pub fn route(mut self, path: &str, limit: RateLimit) -> Self {
+        self.routes.insert(path.to_string(), limit);
+        self
+    }
+
+    /// Límites por defecto del servidor: las rutas que lanzan hilos o
+    /// duermen son las más restringidas
+    pub fn builtin() -> Self {
+        RateLimiter::new(RateLimit::new(50.0, 100.0, 16))
+            .route("/loadtest", RateLimit::new(0.2, 2.0, 1))
+            .route("/simulate", RateLimit::new(2.0, 5.0, 4))
+            .route("/sleep", RateLimit::new(2.0, 5.0, 4))
+            .route("/createfile", RateLimit::new(5.0, 10.0, 2))
+    }
+
+    fn limit_for(&self, path: &str) -> RateLimit {
+        self.routes.get(path).copied().unwrap_or(self.default)
+    }
+
+    /// Intenta tomar un token y un hueco de concurrencia.
+    ///   - Ok si se admite la petición (hay que llamar a `release` al terminar)
+    ///   - Err(segundos) a esperar antes de reintentar
+    fn acquire(&self, ip: IpAddr, path: &str) -> Result<(), u64> {
+        let limit = self.limit_for(path);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|(_, p), b| {
+                let l = self.limit_for(p);
+                b.in_flight > 0 || b.tokens + now.duration_since(b.last).as_secs_f64() * l.per_second < l.burst
+            });
+        }
+
+        let bucket = buckets.entry((ip, path.to_string())).or_insert(Bucket {
+            tokens: limit.burst,
+            last: now,
+            in_flight: 0,
+        });
+
+        let elapsed = now.duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(limit.burst);
+        bucket.last = now;
+
+        if bucket.in_flight >= limit.max_concurrent {
+            return Err(1);
+        }
+        if bucket.tokens < 1.0 {
+            let wait = if limit.per_second > 0.0 {
+                ((1.0 - bucket.tokens) / limit.per_second).ceil() as u64
+            } else {
+                u64::MAX
+            };
+            return Err(wait.max(1));
+        }
+
+        bucket.tokens -= 1.0;
+        bucket.in_flight += 1;
+        Ok(())
+    }
+}
+
+/// Libera el hueco de concurrencia aunque el handler haga panic. Si la
+/// petición cede el hilo viaja en `Request.held` hasta que termina
+struct InFlight {
+    buckets: Arc<Buckets>,
+    ip: IpAddr,
+    path: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        release(&self.buckets, self.ip, &self.path);
+    }
+}
+
+impl Middleware for RateLimiter {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        let enabled = state.lock().unwrap().config.rate_limit;
+        let Some(ip) = req.peer.map(|p| p.ip()).filter(|_| enabled) else {
+            return next.run(req, state);
+        };
+
+        match self.acquire(ip, &req.path) {
+            Ok(()) => {
+                let guard = InFlight { buckets: self.buckets.clone(), ip, path: req.path.clone() };
+                let resp = next.run(req, state);
+                // Las porciones siguientes ya no pasan por los middleware
+                if req.yielded {
+                    req.held.push(Arc::new(guard));
+                }
+                resp
+            }
+            Err(retry_after) => {
+                {
+                    let mut st = state.lock().unwrap();
+                    st.rate_limited += 1;
+                }
+                http::with_header(
+                    format!(
+                        "HTTP/1.0 429 Too Many Requests\r\n\r\nDemasiadas peticiones a '{}', reintente en {} segundo(s)\n",
+                        req.path, retry_after
+                    ),
+                    "Retry-After",
+                    &retry_after.to_string(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::dispatch_slice;
+    use crate::state::new_state;
+    use std::time::Duration;
+
+    fn ok(_: &mut Request, _: &SharedState) -> String {
+        "HTTP/1.0 200 OK\r\n\r\n".to_string()
+    }
+
+    fn from(ip: &str, path: &str) -> Request {
+        let mut req = Request::get(path);
+        req.peer = Some(format!("{}:5000", ip).parse().unwrap());
+        req
+    }
+
+    #[test]
+    fn token_bucket_rejects_with_retry_after() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> =
+            vec![Arc::new(RateLimiter::new(RateLimit::new(0.5, 2.0, 10)))];
+
+        for _ in 0..2 {
+            let resp = Next::new(&chain, &ok).run(&mut from("10.0.0.1", "/x"), &state);
+            assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        }
+        let resp = Next::new(&chain, &ok).run(&mut from("10.0.0.1", "/x"), &state);
+        assert!(resp.starts_with("HTTP/1.0 429 Too Many Requests"));
+        assert!(resp.contains("Retry-After: 2\r\n"));
+        assert_eq!(state.lock().unwrap().rate_limited, 1);
+
+        // Otra IP y otra ruta tienen sus propios buckets
+        let resp = Next::new(&chain, &ok).run(&mut from("10.0.0.2", "/x"), &state);
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        let resp = Next::new(&chain, &ok).run(&mut from("10.0.0.1", "/y"), &state);
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn per_route_concurrency_cap() {
+        let limiter = RateLimiter::new(RateLimit::new(100.0, 100.0, 10))
+            .route("/loadtest", RateLimit::new(100.0, 100.0, 1));
+        let ip: IpAddr = "10.0.0.3".parse().unwrap();
+
+        assert!(limiter.acquire(ip, "/loadtest").is_ok());
+        assert_eq!(limiter.acquire(ip, "/loadtest"), Err(1));
+        release(&limiter.buckets, ip, "/loadtest");
+        assert!(limiter.acquire(ip, "/loadtest").is_ok());
+    }
+
+    #[test]
+    fn requests_without_peer_are_not_limited() {
+        let state = new_state();
+        let chain: Vec<Arc<dyn Middleware>> =
+            vec![Arc::new(RateLimiter::new(RateLimit::new(0.0, 0.0, 0)))];
+        let resp = Next::new(&chain, &ok).run(&mut Request::get("/x"), &state);
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn yielded_requests_keep_their_concurrency_slot() {
+        // Como con --scheduler=rr: /sleep cede el hilo en cada porción
+        let state = new_state();
+        let quantum = Duration::from_millis(2);
+        let mut running: Vec<Request> = (0..4).map(|_| from("10.0.0.4", "/sleep?ms=50")).collect();
+        for req in &mut running {
+            assert_eq!(dispatch_slice(req, &state, quantum), None);
+        }
+        // /sleep admite 4 simultáneas por IP: la quinta espera aunque las
+        // otras estén fuera de los middleware
+        let resp = dispatch_slice(&mut from("10.0.0.4", "/sleep?ms=1"), &state, quantum).unwrap();
+        assert!(resp.starts_with("HTTP/1.0 429"), "{}", resp);
+
+        for mut req in running {
+            while dispatch_slice(&mut req, &state, quantum).is_none() {}
+        }
+        let resp = dispatch_slice(&mut from("10.0.0.4", "/sleep?ms=1"), &state, quantum).unwrap();
+        assert!(resp.starts_with("HTTP/1.0 200 OK"), "{}", resp);
+    }
+}
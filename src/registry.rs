@@ -0,0 +1,638 @@
+use crate::http::Request;
+use crate::middleware::Middleware;
+use crate::state::SharedState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tipo de un parámetro de query
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+    /// Entero con signo (i64)
+    Int,
+    /// Entero sin signo (u64)
+    UInt,
+    /// Texto libre
+    Text,
+    /// Duración con unidad (`250ms`, `1.5s`, `2m`); sin unidad son segundos
+    Duration,
+    /// Número real (f64)
+    Float,
+}
+
+/// Esquema de un parámetro: nombre, tipo, obligatoriedad, default, rango
+/// y, para texto, la lista de valores permitidos
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub kind: ParamKind,
+    pub required: bool,
+    pub default: Option<&'static str>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub choices: &'static [&'static str],
+    pub description: &'static str,
+}
+
+impl ParamSpec {
+    /// Parámetro obligatorio del tipo indicado, sin rango
+    pub const fn required(name: &'static str, kind: ParamKind, description: &'static str) -> Self {
+        ParamSpec {
+            name, kind, required: true, default: None, min: None, max: None, choices: &[], description,
+        }
+    }
+
+    /// Parámetro opcional con valor por defecto
+    pub const fn optional(
+        name: &'static str,
+        kind: ParamKind,
+        default: &'static str,
+        description: &'static str,
+    ) -> Self {
+        ParamSpec {
+            name, kind, required: false, default: Some(default), min: None, max: None, choices: &[], description,
+        }
+    }
+
+    /// Parámetro opcional sin valor por defecto (el handler decide qué hacer si falta)
+    pub const fn optional_no_default(name: &'static str, kind: ParamKind, description: &'static str) -> Self {
+        ParamSpec { required: false, ..ParamSpec::required(name, kind, description) }
+    }
+
+    /// Fija el rango permitido `[min, max]` (cualquiera de los dos puede faltar)
+    pub const fn range(mut self, min: Option<i64>, max: Option<i64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Restringe un parámetro de texto a un conjunto cerrado de valores
+    pub const fn one_of(mut self, choices: &'static [&'static str]) -> Self {
+        self.choices = choices;
+        self
+    }
+
+    /// Descripción corta del tipo para /help
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            ParamKind::Int => "entero",
+            ParamKind::UInt if self.min.unwrap_or(0) >= 1 => "entero positivo",
+            ParamKind::UInt => "entero no negativo",
+            ParamKind::Text => "texto",
+            ParamKind::Duration => "duración",
+            ParamKind::Float => "número",
+        }
+    }
+}
+
+/// Configuración del pool de workers de una ruta
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PoolSpec {
+    /// Hilos al arrancar
+    pub workers: usize,
+    /// Mínimo al que se achica el pool tras estar ocioso
+    pub min: usize,
+    /// Máximo al que crece cuando la cola se llena
+    pub max: usize,
+    /// false: la ruta no tiene pool propio y la atiende el pool compartido
+    pub dedicated: bool,
+    /// Con el pool propio saturado, las peticiones nuevas pasan al compartido
+    pub borrow: bool,
+}
+
+impl PoolSpec {
+    /// Sin pool propio: la ruta usa el pool compartido
+    pub const DEFAULT: PoolSpec = PoolSpec { workers: 0, min: 0, max: 0, dedicated: false, borrow: false };
+
+    /// Pool propio de `workers` hilos (puede crecer al doble y achicarse a uno)
+    pub const fn sized(workers: usize) -> PoolSpec {
+        PoolSpec { workers, min: 1, max: workers * 2, dedicated: true, borrow: false }
+    }
+}
+
+/// Handler de una ruta: recibe la petición (con `params` ya validados)
+/// y el estado compartido, y devuelve la respuesta HTTP completa
+pub trait Handler: Send + Sync {
+    fn handle(&self, req: &Request, state: SharedState) -> String;
+
+    /// Ejecuta a lo sumo `slice` de trabajo. Los handlers cooperativos
+    /// guardan su avance en `req.resume` y devuelven `Step::Yield` para
+    /// volver a la cola; los demás corren completos
+    fn step(&self, req: &mut Request, state: SharedState, slice: Duration) -> Step {
+        let _ = slice;
+        Step::Done(self.handle(req, state))
+    }
+}
+
+/// Resultado de una porción de trabajo de un handler
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Done(String),
+    Yield,
+}
+
+/// Handler cooperativo a partir de una función que avanza por porciones
+pub struct Sliced<F>(pub F);
+
+impl<F> Handler for Sliced<F>
+where
+    F: Fn(&mut Request, SharedState, Duration) -> Step + Send + Sync,
+{
+    fn handle(&self, req: &Request, state: SharedState) -> String {
+        let mut req = req.clone();
+        loop {
+            if let Step::Done(resp) = (self.0)(&mut req, state.clone(), Duration::MAX) {
+                return resp;
+            }
+        }
+    }
+
+    fn step(&self, req: &mut Request, state: SharedState, slice: Duration) -> Step {
+        (self.0)(req, state, slice)
+    }
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&Request, SharedState) -> String + Send + Sync,
+{
+    fn handle(&self, req: &Request, state: SharedState) -> String {
+        self(req, state)
+    }
+}
+
+/// Definición de una ruta: path, métodos, esquema de parámetros, descripción y pool
+#[derive(Clone, Copy, Debug)]
+pub struct RouteSpec {
+    pub path: &'static str,
+    pub methods: &'static [&'static str],
+    pub params: &'static [ParamSpec],
+    pub description: &'static str,
+    pub pool: PoolSpec,
+    /// Costo estimado (ms) de una petición, para el scheduler SJF
+    pub cost: CostFn,
+    /// true: la ruta (sin parámetros) responde 400 si llega una query; si
+    /// no, la query se ignora
+    pub strict_query: bool,
+}
+
+/// Estima el costo de una petición a partir de sus parámetros validados
+pub type CostFn = fn(&Params) -> f64;
+
+/// Costo por defecto: todas las peticiones pesan lo mismo
+pub fn unit_cost(_: &Params) -> f64 {
+    1.0
+}
+
+impl RouteSpec {
+    /// Misma ruta con un pool de `workers` hilos propio (puede crecer al
+    /// doble y achicarse a uno)
+    pub const fn workers(mut self, workers: usize) -> Self {
+        self.pool = PoolSpec { borrow: self.pool.borrow, ..PoolSpec::sized(workers) };
+        self
+    }
+
+    /// Límites del auto-escalado del pool propio (el tamaño inicial queda dentro)
+    pub const fn scale(mut self, min: usize, max: usize) -> Self {
+        let workers = if self.pool.workers < min {
+            min
+        } else if self.pool.workers > max {
+            max
+        } else {
+            self.pool.workers
+        };
+        self.pool = PoolSpec { workers, min, max, dedicated: true, borrow: self.pool.borrow };
+        self
+    }
+
+    /// Con el pool propio saturado, atender en el pool compartido
+    pub const fn borrow_shared(mut self) -> Self {
+        self.pool.borrow = true;
+        self
+    }
+
+    /// Ruta sin parámetros que rechaza (400) cualquier query
+    pub const fn strict_query(mut self) -> Self {
+        self.strict_query = true;
+        self
+    }
+
+    /// Misma ruta con otra estimación de costo
+    pub const fn cost(mut self, cost: CostFn) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Costo estimado de una query (el de por defecto si no valida)
+    pub fn estimate(&self, query: &str) -> f64 {
+        match validate(self.params, query) {
+            Ok(params) => (self.cost)(&params),
+            Err(_) => unit_cost(&Params::default()),
+        }
+    }
+
+    /// Forma de uso, p. ej. `/random?count=...&min=...&max=...`
+    pub fn usage(&self) -> String {
+        if self.params.is_empty() {
+            return self.path.to_string();
+        }
+        let args: Vec<String> = self.params.iter().map(|p| format!("{}=...", p.name)).collect();
+        format!("{}?{}", self.path, args.join("&"))
+    }
+}
+
+/// Ruta registrada: su definición, el handler que la atiende y los
+/// middleware propios de la ruta
+#[derive(Clone)]
+pub struct Route {
+    pub spec: RouteSpec,
+    pub handler: Arc<dyn Handler>,
+    pub middleware: Vec<Arc<dyn Middleware>>,
+}
+
+/// Registro de rutas. Se llena al arrancar (ver `router::builtin_registry`)
+/// y es la única fuente para el despacho, los pools, /help y /openapi.json
+#[derive(Clone, Default)]
+pub struct Registry {
+    routes: Vec<Route>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra una ruta; si el path ya existía, reemplaza la anterior
+    /// (conservando sus middleware)
+    pub fn register(&mut self, spec: RouteSpec, handler: impl Handler + 'static) -> &mut Self {
+        let handler: Arc<dyn Handler> = Arc::new(handler);
+        match self.routes.iter_mut().find(|r| r.spec.path == spec.path) {
+            Some(existing) => {
+                existing.spec = spec;
+                existing.handler = handler;
+            }
+            None => self.routes.push(Route { spec, handler, middleware: Vec::new() }),
+        }
+        self
+    }
+
+    /// Añade un middleware global; se ejecutan en orden de registro y
+    /// envuelven también las respuestas 404
+    pub fn wrap(&mut self, mw: impl Middleware + 'static) -> &mut Self {
+        self.middleware.push(Arc::new(mw));
+        self
+    }
+
+    /// Añade un middleware a una ruta ya registrada; corre dentro de los globales
+    ///   - Panic si la ruta no existe (error de configuración al arrancar)
+    pub fn wrap_route(&mut self, path: &str, mw: impl Middleware + 'static) -> &mut Self {
+        let route = self
+            .routes
+            .iter_mut()
+            .find(|r| r.spec.path == path)
+            .unwrap_or_else(|| panic!("wrap_route: ruta '{}' no registrada", path));
+        route.middleware.push(Arc::new(mw));
+        self
+    }
+
+    pub fn middleware(&self) -> &[Arc<dyn Middleware>] {
+        &self.middleware
+    }
+
+    pub fn find(&self, path: &str) -> Option<&Route> {
+        self.routes.iter().find(|r| r.spec.path == path)
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    pub fn specs(&self) -> impl Iterator<Item = &RouteSpec> {
+        self.routes.iter().map(|r| &r.spec)
+    }
+}
+
+/// Valor ya validado de un parámetro
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    UInt(u64),
+    Text(String),
+    Duration(Duration),
+    Float(f64),
+}
+
+/// Parámetros validados de una petición
+#[derive(Clone, Debug, Default)]
+pub struct Params {
+    values: HashMap<&'static str, Value>,
+}
+
+impl Params {
+    pub fn i64(&self, name: &str) -> Option<i64> {
+        match self.values.get(name)? {
+            Value::Int(v) => Some(*v),
+            Value::UInt(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        match self.values.get(name)? {
+            Value::UInt(v) => Some(*v),
+            Value::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn usize(&self, name: &str) -> Option<usize> {
+        self.u64(name).and_then(|v| usize::try_from(v).ok())
+    }
+
+    pub fn f64(&self, name: &str) -> Option<f64> {
+        match self.values.get(name)? {
+            Value::Float(v) => Some(*v),
+            Value::Int(v) => Some(*v as f64),
+            Value::UInt(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        match self.values.get(name)? {
+            Value::Duration(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    pub fn str(&self, name: &str) -> Option<&str> {
+        match self.values.get(name)? {
+            Value::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Separa una query `k=v&k2=v2` en pares; conserva la primera aparición de cada clave
+pub fn query_pairs(query: &str) -> HashMap<&str, &str> {
+    let mut map = HashMap::new();
+    for p in query.split('&') {
+        if let Some((k, v)) = p.split_once('=') {
+            map.entry(k).or_insert(v);
+        }
+    }
+    map
+}
+
+/// Valida una query contra el esquema de parámetros de una ruta
+///   - Err si falta un parámetro obligatorio
+///   - Err si un valor no tiene el tipo esperado o está fuera de rango
+pub fn validate(specs: &[ParamSpec], query: &str) -> Result<Params, String> {
+    let pairs = query_pairs(query);
+    let mut params = Params::default();
+
+    for spec in specs {
+        let raw = match pairs.get(spec.name).copied().or(spec.default) {
+            Some(v) => v,
+            None if spec.required => return Err(format!("Parámetro '{}' requerido", spec.name)),
+            None => continue,
+        };
+        let value = parse_value(spec, raw)?;
+        params.values.insert(spec.name, value);
+    }
+
+    Ok(params)
+}
+
+fn parse_value(spec: &ParamSpec, raw: &str) -> Result<Value, String> {
+    let type_err = || format!("Parámetro '{}' debe ser un {}", spec.name, spec.kind_name());
+    let value = match spec.kind {
+        ParamKind::Text if !spec.choices.is_empty() && !spec.choices.contains(&raw) => {
+            return Err(format!(
+                "Parámetro '{}' debe ser uno de: {}",
+                spec.name,
+                spec.choices.join(", ")
+            ));
+        }
+        ParamKind::Text => return Ok(Value::Text(raw.to_string())),
+        ParamKind::Duration => {
+            return parse_duration(raw).map(Value::Duration).map_err(|_| {
+                format!("Parámetro '{}' debe ser una duración (p. ej. 250ms, 1.5s, 2m)", spec.name)
+            });
+        }
+        ParamKind::Int => Value::Int(raw.parse::<i64>().map_err(|_| type_err())?),
+        ParamKind::UInt => Value::UInt(raw.parse::<u64>().map_err(|_| type_err())?),
+        ParamKind::Float => Value::Float(
+            raw.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(type_err)?,
+        ),
+    };
+
+    // Comparación con el rango según el tipo del valor
+    let below = |min: i64| match value {
+        Value::Float(v) => v < min as f64,
+        Value::Int(v) => v < min,
+        Value::UInt(v) => i128::from(v) < i128::from(min),
+        _ => false,
+    };
+    let above = |max: i64| match value {
+        Value::Float(v) => v > max as f64,
+        Value::Int(v) => v > max,
+        Value::UInt(v) => i128::from(v) > i128::from(max),
+        _ => false,
+    };
+
+    if let Some(min) = spec.min.filter(|&min| below(min)) {
+        return Err(if min == 1 && spec.kind != ParamKind::Float {
+            format!("Parámetro '{}' debe ser mayor que cero", spec.name)
+        } else {
+            format!("Parámetro '{}' debe ser mayor o igual que {}", spec.name, min)
+        });
+    }
+    if let Some(max) = spec.max.filter(|&max| above(max)) {
+        return Err(format!("Parámetro '{}' debe ser menor o igual que {}", spec.name, max));
+    }
+
+    Ok(value)
+}
+
+/// Parsea una duración como `10`, `1.5s`, `250ms` o `2m`
+///   - Err si el número no es válido o es negativo
+pub fn parse_duration(v: &str) -> Result<Duration, String> {
+    let err = || format!("Duración inválida: '{}'", v);
+    let (num, factor_ms) = if let Some(n) = v.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = v.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = v.strip_suffix('m') {
+        (n, 60_000.0)
+    } else {
+        (v, 1000.0)
+    };
+    let n: f64 = num.parse().map_err(|_| err())?;
+    if !n.is_finite() || n < 0.0 {
+        return Err(err());
+    }
+    Duration::try_from_secs_f64(n * factor_ms / 1000.0).map_err(|_| err())
+}
+
+/// Genera el texto de /help a partir de las rutas registradas
+pub fn render_help(registry: &Registry) -> String {
+    let mut out = String::from("Rutas disponibles:\n");
+    for r in registry.specs() {
+        out.push_str(&format!("{}\n", r.usage()));
+        out.push_str(&format!("    -> {}: {}\n", r.methods.join(", "), r.description));
+        for p in r.params {
+            let mut line = format!("       {}: {}", p.name, p.kind_name());
+            if p.required {
+                line.push_str(", requerido");
+            } else if let Some(d) = p.default {
+                line.push_str(&format!(", opcional (por defecto {})", d));
+            } else {
+                line.push_str(", opcional");
+            }
+            match (p.min, p.max) {
+                (Some(a), Some(b)) => line.push_str(&format!(", rango [{}, {}]", a, b)),
+                (Some(a), None) => line.push_str(&format!(", >= {}", a)),
+                (None, Some(b)) => line.push_str(&format!(", <= {}", b)),
+                (None, None) => {}
+            }
+            if !p.choices.is_empty() {
+                line.push_str(&format!(", valores: {}", p.choices.join("|")));
+            }
+            if !p.description.is_empty() {
+                line.push_str(&format!(" — {}", p.description));
+            }
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Forma serializable de una ruta (sin el handler)
+#[derive(Serialize)]
+struct RouteInfo<'a> {
+    path: &'a str,
+    usage: String,
+    methods: &'a [&'a str],
+    description: &'a str,
+    params: &'a [ParamSpec],
+    pool: PoolSpec,
+}
+
+/// Versión estructurada de /help (`/help?format=json`)
+pub fn help_json(registry: &Registry) -> serde_json::Value {
+    let routes: Vec<RouteInfo> = registry
+        .specs()
+        .map(|r| RouteInfo {
+            path: r.path,
+            usage: r.usage(),
+            methods: r.methods,
+            description: r.description,
+            params: r.params,
+            pool: r.pool,
+        })
+        .collect();
+    serde_json::json!({ "routes": routes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: &[ParamSpec] = &[
+        ParamSpec::required("n", ParamKind::UInt, "").range(Some(1), Some(10)),
+        ParamSpec::optional("k", ParamKind::Int, "-3", ""),
+        ParamSpec::required("t", ParamKind::Text, ""),
+        ParamSpec::optional("f", ParamKind::Text, "a", "").one_of(&["a", "b"]),
+        ParamSpec::optional("p", ParamKind::Float, "0.5", "").range(Some(0), Some(1)),
+    ];
+
+    #[test]
+    fn validate_success_and_default() {
+        let p = validate(SPECS, "n=4&t=hola").unwrap();
+        assert_eq!(p.usize("n"), Some(4));
+        assert_eq!(p.i64("k"), Some(-3));
+        assert_eq!(p.str("t"), Some("hola"));
+    }
+
+    #[test]
+    fn validate_missing_and_invalid() {
+        assert_eq!(validate(SPECS, "t=x").unwrap_err(), "Parámetro 'n' requerido");
+        assert_eq!(
+            validate(SPECS, "n=abc&t=x").unwrap_err(),
+            "Parámetro 'n' debe ser un entero positivo"
+        );
+        assert_eq!(validate(SPECS, "n=0&t=x").unwrap_err(), "Parámetro 'n' debe ser mayor que cero");
+        assert_eq!(
+            validate(SPECS, "n=11&t=x").unwrap_err(),
+            "Parámetro 'n' debe ser menor o igual que 10"
+        );
+    }
+
+    #[test]
+    fn validate_keeps_first_occurrence() {
+        let p = validate(SPECS, "n=2&n=3&t=a").unwrap();
+        assert_eq!(p.u64("n"), Some(2));
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("10").unwrap(), Duration::from_secs(10));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert!(parse_duration("abc").is_err());
+        assert!(parse_duration("-1s").is_err());
+    }
+
+    #[test]
+    fn validate_float_range() {
+        assert_eq!(validate(SPECS, "n=1&t=x").unwrap().f64("p"), Some(0.5));
+        assert_eq!(validate(SPECS, "n=1&t=x&p=0.25").unwrap().f64("p"), Some(0.25));
+        assert_eq!(
+            validate(SPECS, "n=1&t=x&p=1.5").unwrap_err(),
+            "Parámetro 'p' debe ser menor o igual que 1"
+        );
+        assert_eq!(validate(SPECS, "n=1&t=x&p=x").unwrap_err(), "Parámetro 'p' debe ser un número");
+    }
+
+    #[test]
+    fn validate_choices() {
+        assert_eq!(validate(SPECS, "n=1&t=x&f=b").unwrap().str("f"), Some("b"));
+        assert_eq!(
+            validate(SPECS, "n=1&t=x&f=c").unwrap_err(),
+            "Parámetro 'f' debe ser uno de: a, b"
+        );
+    }
+
+    #[test]
+    fn register_adds_and_replaces_routes() {
+        const SPEC: RouteSpec = RouteSpec {
+            path: "/ping",
+            methods: &["GET"],
+            params: &[],
+            description: "",
+            pool: PoolSpec::DEFAULT,
+            cost: unit_cost,
+            strict_query: false,
+        };
+        let mut reg = Registry::new();
+        reg.register(SPEC, |_: &Request, _: SharedState| "uno".to_string());
+        reg.register(SPEC, |_: &Request, _: SharedState| "dos".to_string());
+        assert_eq!(reg.routes().len(), 1);
+
+        let route = reg.find("/ping").unwrap();
+        let resp = route.handler.handle(&Request::get("/ping"), crate::state::new_state());
+        assert_eq!(resp, "dos");
+    }
+}
@@ -1,126 +1,322 @@
 use crate::state::SharedState;
-use crate::handlers;
+use crate::handlers::{self, *};
+use crate::http::Request;
+use crate::middleware::{ConnectionCounter, Next};
+use crate::chaos::{self, ChaosLayer};
+use crate::pool;
+use crate::random;
+use crate::trace;
+use crate::ratelimit::RateLimiter;
+use crate::singleflight::Singleflight;
+use crate::cache::{self, CacheLayer};
+use crate::registry::{self, Params, PoolSpec, Registry, RouteSpec, Sliced, Step};
+use std::time::Duration;
 
-pub fn route(path_and_query: &str, state: SharedState) -> String {
-    // Separa ruta y query (en caso de que haya '?')
-    let mut parts = path_and_query.splitn(2, '?');
-    let path = parts.next().unwrap_or("");
-    let query = parts.next().unwrap_or("");
-
-    match path {
-        "/status" => {handlers::handle_status(state)}
-        "/fibonacci" => {
-            match handlers::parse_fib_param(query) {
-                Ok(n)    => handlers::handle_fibonacci(n),
-                Err(msg) => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}",
-                    msg
-                ),
-            }
-        }
-        "/createfile" => {
-            match handlers::parse_createfile_params(query) {
-                Ok((name, content, repeat)) => {
-                    handlers::handle_createfile(&name, &content, repeat)
-                }
-                Err(msg) => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
-            }
-        }
-        "/deletefile" => {
-            match handlers::parse_deletefile_param(query) {
-                Ok(name) => handlers::handle_deletefile(&name),
-                Err(msg)  => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
-            }
-        }
-        "/reverse" => {
-            match handlers::parse_text_param(query) {
-                Ok(text)  => handlers::handle_reverse(&text),
-                Err(msg)  => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
-            }
-        }
-        "/toupper" => {
-            match handlers::parse_text_param(query) {
-                Ok(text)  => handlers::handle_toupper(&text),
-                Err(msg)  => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
-            }
-        }
-        "/random" => {
-            match handlers::parse_random_params(query) {
-                Ok((count, min, max)) => handlers::handle_random(count, min, max),
-                Err(msg) => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
+const GET: &[&str] = &["GET"];
+const GET_POST: &[&str] = &["GET", "POST"];
+
+/// Definición de una ruta GET atendida por el pool compartido
+const fn get(path: &'static str, params: &'static [registry::ParamSpec], description: &'static str) -> RouteSpec {
+    RouteSpec { path, methods: GET, params, description, pool: PoolSpec::DEFAULT, cost: registry::unit_cost, strict_query: false }
+}
+
+/// Ruta de texto: la entrada llega en `text` (GET) o en el cuerpo (POST)
+const fn text_route(path: &'static str, description: &'static str) -> RouteSpec {
+    RouteSpec { path, methods: GET_POST, params: TEXT_PARAMS, description, pool: PoolSpec::DEFAULT, cost: registry::unit_cost, strict_query: false }
+}
+
+/// Rutas cuyas respuestas se cachean (`CacheLayer`), con su vencimiento por
+/// defecto; `--cache-ttl` solo puede nombrar estas
+pub fn cached_routes() -> Vec<(&'static str, Duration)> {
+    let mut routes = vec![("/fibonacci", Duration::from_secs(3600)), ("/hash", Duration::from_secs(600))];
+    routes.extend(TextOp::ALL.iter().map(|op| (op.path(), Duration::from_secs(300))));
+    routes
+}
+
+/// Registro con los comandos incluidos en el servidor. Otros módulos o crates
+/// pueden añadir los suyos con `Registry::register` antes de arrancar
+pub fn builtin_registry() -> Registry {
+    let mut reg = Registry::new();
+    reg.wrap(ConnectionCounter).wrap(RateLimiter::builtin()).wrap(ChaosLayer);
+    reg.register(
+        get("/status", &[], "devuelve uptime, total_connections, pid y workers en JSON").workers(1),
+        |_: &Request, state: SharedState| handle_status(state),
+    );
+    for op in TextOp::ALL {
+        reg.register(text_route(op.path(), op.description()), move |req: &Request, _: SharedState| {
+            match text_input(req) {
+                Ok(text) => handle_text(op, &text),
+                Err(msg) => bad_request(&msg),
             }
-        }
-        "/timestamp" => {
-            if query.is_empty() {
-                handlers::handle_timestamp()
-            } else {
-                format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\nRuta '/timestamp' no acepta parámetros\n"
-                )
+        });
+    }
+    reg.register(
+        get("/fibonacci", FIB_PARAMS, "calcula Fibonacci recursivo")
+            .workers(4)
+            .borrow_shared()
+            .cost(fibonacci_cost),
+        |req: &Request, _: SharedState| handle_fibonacci(req.params.usize("num").unwrap_or_default()),
+    )
+    .register(
+        get("/createfile", CREATEFILE_PARAMS, "crea archivo con contenido repetido"),
+        |req: &Request, _: SharedState| handle_createfile(
+            req.params.str("name").unwrap_or_default(),
+            req.params.str("content").unwrap_or_default(),
+            req.params.usize("repeat").unwrap_or(1),
+        ),
+    )
+    .register(
+        get("/deletefile", DELETEFILE_PARAMS, "elimina archivo"),
+        |req: &Request, _: SharedState| handle_deletefile(req.params.str("name").unwrap_or_default()),
+    )
+    .register(
+        get("/random", RANDOM_PARAMS, "valores aleatorios (distribuciones, cadenas, UUID, listas) en JSON, CSV o líneas").workers(4),
+        |req: &Request, state: SharedState| match random::random_spec(&req.params) {
+            Ok(spec) => {
+                let seed = match req.params.u64("seed") {
+                    Some(seed) => seed,
+                    None => state.lock().unwrap().next_random_seed(),
+                };
+                random::respond(&spec, seed, req.sink.as_ref())
             }
-        }
-        "/hash" => {
-            match handlers::parse_text_param(query) {
-                Ok(text)  => handlers::handle_hash(&text),
-                Err(msg)  => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
+            Err(msg) => bad_request(&msg),
+        },
+    )
+    .register(
+        get("/timestamp", &[], "hora actual UTC en texto plano").strict_query(),
+        |_: &Request, _: SharedState| handle_timestamp(),
+    )
+    .register(
+        text_route("/hash", "SHA-256 del texto en hex (`text` se toma tal cual, sin decodificar)"),
+        |req: &Request, _: SharedState| match raw_text_input(req) {
+            Ok(text) => handle_hash(&text),
+            Err(msg) => bad_request(&msg),
+        },
+    )
+    .register(
+        get("/isprime", ISPRIME_PARAMS, "test de primalidad Miller–Rabin").workers(2),
+        |req: &Request, state: SharedState| handle_isprime(
+            req.params.u64("n").unwrap_or_default(),
+            &compute_deadline(req, &state),
+        ),
+    )
+    .register(
+        get("/factor", FACTOR_PARAMS, "factorización en primos (Pollard rho)").workers(2),
+        |req: &Request, state: SharedState| handle_factor(
+            req.params.u64("n").unwrap_or_default(),
+            &compute_deadline(req, &state),
+        ),
+    )
+    .register(
+        get("/pi", PI_PARAMS, "dígitos de pi (algoritmo spigot)").workers(2).cost(pi_cost),
+        |req: &Request, state: SharedState| handle_pi(
+            req.params.usize("digits").unwrap_or(1),
+            &compute_deadline(req, &state),
+        ),
+    )
+    .register(
+        get("/mandelbrot", MANDELBROT_PARAMS, "conjunto de Mandelbrot como imagen PGM").workers(2).cost(mandelbrot_cost),
+        |req: &Request, state: SharedState| handle_mandelbrot(
+            &mandelbrot_args(&req.params),
+            &compute_deadline(req, &state),
+        ),
+    )
+    .register(
+        get("/matrixmul", MATRIXMUL_PARAMS, "producto de dos matrices aleatorias de size x size")
+            .workers(2)
+            .cost(matrixmul_cost),
+        |req: &Request, state: SharedState| {
+            let seed = match req.params.u64("seed") {
+                Some(seed) => seed,
+                None => state.lock().unwrap().next_random_seed(),
+            };
+            handle_matrixmul(req.params.usize("size").unwrap_or(1), seed, &compute_deadline(req, &state))
+        },
+    )
+    .register(
+        get("/simulate", SIMULATE_PARAMS, "simula tarea que duerme o quema CPU (precisión de ms, jitter opcional)")
+            .workers(4)
+            .borrow_shared()
+            .cost(delay_cost),
+        Sliced(|req: &mut Request, _: SharedState, slice: Duration| match delay_args(&req.params) {
+            Ok(delay) => {
+                let task = req.params.str("task").unwrap_or_default().to_string();
+                let mode = simulate_mode(&req.params);
+                simulate_step(req, &delay, task, mode, slice)
             }
-        }
-        "/simulate" => {
-            match handlers::parse_simulate_params(query) {
-                Ok((seconds, task_name)) => handlers::handle_simulate(seconds, task_name),
-                Err(msg) => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
+            Err(msg) => Step::Done(bad_request(&msg)),
+        }),
+    )
+    .register(
+        get("/sleep", SLEEP_PARAMS, "simula retardo simple (precisión de ms, jitter opcional)")
+            .workers(4)
+            .borrow_shared()
+            .cost(delay_cost),
+        Sliced(|req: &mut Request, _: SharedState, slice: Duration| match delay_args(&req.params) {
+            Ok(delay) => sleep_step(req, &delay, slice),
+            Err(msg) => Step::Done(bad_request(&msg)),
+        }),
+    )
+    .register(
+        get("/loadtest", LOADTEST_PARAMS, "carga concurrente (sleep, cpu, io o mixed) en un pool acotado; reporte JSON de latencias"),
+        |req: &Request, state: SharedState| {
+            let (max_tasks, executor) = {
+                let st = state.lock().unwrap();
+                (st.config.loadtest_max_tasks, st.loadtest_executor())
+            };
+            match loadtest_plan(&req.params) {
+                Ok(plan) if plan.tasks > max_tasks => bad_request(&format!(
+                    "Parámetro 'tasks' debe ser menor o igual que {}",
+                    max_tasks
+                )),
+                Ok(plan) => handle_loadtest(&plan, &executor),
+                Err(msg) => bad_request(&msg),
             }
-        }
-        "/sleep" => {
-            match handlers::parse_sleep_param(query) {
-                Ok(seconds) => handlers::handle_sleep(seconds),
-                Err(msg)    => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
+        },
+    )
+    .register(
+        get("/help", HELP_PARAMS, "muestra esta ayuda (texto o JSON)"),
+        |req: &Request, state: SharedState| {
+            let registry = state.lock().unwrap().registry.clone();
+            match req.params.str("format") {
+                Some("json") => handle_help_json(&registry),
+                _ => handlers::handle_help(&registry),
             }
+        },
+    )
+    .register(
+        get("/openapi.json", &[], "descripción OpenAPI 3 de la API"),
+        |_: &Request, state: SharedState| {
+            let registry = state.lock().unwrap().registry.clone();
+            handle_openapi(&registry)
+        },
+    )
+    .register(
+        get("/trace", trace::TRACE_PARAMS, "línea de tiempo de los workers en formato Chrome Trace Event"),
+        |req: &Request, state: SharedState| trace::handle_trace(&req.params, &state.lock().unwrap().trace),
+    )
+    .register(
+        get("/admin/chaos", chaos::CHAOS_PARAMS, "inyección de fallas por ruta (latencia, 5xx, cierres, truncado, panic) con semilla")
+            .workers(1),
+        |req: &Request, state: SharedState| chaos::handle_chaos(&req.params, &mut state.lock().unwrap().chaos),
+    )
+    .register(
+        get("/admin/pools", pool::POOLS_PARAMS, "tamaño, pausa, drenado y política de los pools en caliente").workers(1),
+        |req: &Request, state: SharedState| pool::handle_pools(&req.params, &state),
+    )
+    .register(
+        get("/admin/cache", cache::CACHE_PARAMS, "estado de la caché de respuestas; action=flush la vacía").workers(1),
+        |req: &Request, state: SharedState| cache::handle_cache(&req.params, &mut state.lock().unwrap().cache),
+    );
+    // Rutas deterministas: sus respuestas se cachean (primero, por fuera) y
+    // las peticiones idénticas en curso comparten el cálculo
+    for (path, ttl) in cached_routes() {
+        reg.wrap_route(path, CacheLayer::new(ttl));
+    }
+    for path in ["/fibonacci", "/hash", "/reverse", "/toupper"] {
+        reg.wrap_route(path, Singleflight::default());
+    }
+    reg
+}
+
+/// Límite de tiempo de un comando de cálculo: el `timeout` pedido, sin
+/// pasar del `--compute-timeout` del servidor
+fn compute_deadline(req: &Request, state: &SharedState) -> Deadline {
+    let max = state.lock().unwrap().config.compute_timeout;
+    Deadline::after(req.params.duration("timeout").map_or(max, |t| t.min(max))).or_cancel(&req.cancel)
+}
+
+/// Despacha una petición GET (atajo usado por los tests)
+pub fn route(path_and_query: &str, state: SharedState) -> String {
+    dispatch(Request::get(path_and_query), state)
+}
+
+/// Despacha una petición según el registro de rutas del estado, pasando
+/// primero por los middleware globales y luego por los de la ruta:
+///   - 404 si la ruta no existe
+///   - 405 si el método no está permitido
+///   - 400 si los parámetros no cumplen el esquema
+pub fn dispatch(mut req: Request, state: SharedState) -> String {
+    dispatch_slice(&mut req, &state, Duration::MAX).unwrap_or_default()
+}
+
+/// Como `dispatch`, pero un handler cooperativo corre a lo sumo `slice`:
+///   - None si cedió el hilo (`req.yielded`); se retoma llamando de nuevo
+///     con la misma petición, ya sin volver a pasar por los middleware
+pub fn dispatch_slice(req: &mut Request, state: &SharedState, slice: Duration) -> Option<String> {
+    let registry = state.lock().unwrap().registry.clone();
+    if req.yielded {
+        req.yielded = false;
+        return match registry.find(&req.path) {
+            Some(route) => match route.handler.step(req, state.clone(), slice) {
+                Step::Done(resp) => Some(resp),
+                Step::Yield => {
+                    req.yielded = true;
+                    None
+                }
+            },
+            None => Some(not_found(req)),
+        };
+    }
+    let endpoint = |req: &mut Request, state: &SharedState| route_request(&registry, req, state, slice);
+    let resp = Next::new(registry.middleware(), &endpoint).run(req, state);
+    (!req.yielded).then_some(resp)
+}
+
+fn not_found(req: &Request) -> String {
+    format!(
+        "HTTP/1.0 404 Not Found\r\n\r\nRuta no implementada: {}",
+        req.path_and_query()
+    )
+}
+
+fn route_request(registry: &Registry, req: &mut Request, state: &SharedState, slice: Duration) -> String {
+    let Some(route) = registry.find(&req.path) else {
+        // 404 por defecto
+        return not_found(req);
+    };
+    let endpoint = |req: &mut Request, state: &SharedState| call_handler(route, req, state, slice);
+    Next::new(&route.middleware, &endpoint).run(req, state)
+}
+
+fn call_handler(route: &registry::Route, req: &mut Request, state: &SharedState, slice: Duration) -> String {
+    let spec = &route.spec;
+
+    if !spec.methods.contains(&req.method.as_str()) {
+        return format!(
+            "HTTP/1.0 405 Method Not Allowed\r\nAllow: {}\r\n\r\nMétodo {} no permitido en '{}'\n",
+            spec.methods.join(", "),
+            req.method,
+            spec.path
+        );
+    }
+
+    req.params = if spec.params.is_empty() {
+        if spec.strict_query && !req.query.is_empty() {
+            return bad_request(&format!("Ruta '{}' no acepta parámetros", spec.path));
         }
-        "/loadtest" => {
-            match handlers::parse_loadtest_params(query) {
-                Ok((tasks, sleep_secs)) => handlers::handle_loadtest(tasks, sleep_secs),
-                Err(msg) => format!(
-                    "HTTP/1.0 400 Bad Request\r\n\r\n{}\n",
-                    msg
-                ),
-            }
+        Params::default()
+    } else {
+        match registry::validate(spec.params, &req.query) {
+            Ok(p) => p,
+            Err(msg) => return bad_request(&msg),
         }
-        "/help"        => handlers::handle_help(),
-        _ => {
-            // 404 por defecto
-            format!(
-                "HTTP/1.0 404 Not Found\r\n\r\nRuta no implementada: {}",
-                path_and_query
-            )
+    };
+
+    match route.handler.step(req, state.clone(), slice) {
+        Step::Done(resp) => resp,
+        // La respuesta vacía no llega al cliente: la petición vuelve a la cola
+        Step::Yield => {
+            req.yielded = true;
+            String::new()
         }
     }
 }
 
+fn bad_request(msg: &str) -> String {
+    format!("HTTP/1.0 400 Bad Request\r\n\r\n{}\n", msg)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,7 +334,7 @@ mod tests {
     fn route_reverse_ok() {
         let state = new_state();
         let resp = route("/reverse?text=abc", state.clone());
-        assert!(resp.contains("cba\n"));
+        assert!(resp.contains("\"result\":\"cba\""));
     }
 
     #[test]
@@ -182,7 +378,7 @@ mod tests {
     fn route_toupper_ok() {
         let state = new_state();
         let resp = route("/toupper?text=abc", state.clone());
-        assert!(resp.contains("ABC\n"));
+        assert!(resp.contains("\"result\":\"ABC\""));
     }
 
     #[test]
@@ -199,6 +395,13 @@ mod tests {
         assert!(resp.starts_with("HTTP/1.0 400 Bad Request"));
     }
 
+    #[test]
+    fn routes_without_params_ignore_query() {
+        let state = new_state();
+        assert!(route("/status?_=123", state.clone()).starts_with("HTTP/1.0 200 OK"));
+        assert!(route("/help?_=123", state.clone()).starts_with("HTTP/1.0 200 OK"));
+    }
+
     #[test]
     fn route_help() {
         let state = new_state();
@@ -217,6 +420,26 @@ mod tests {
         assert_eq!(body, "[5,5]");
     }
 
+    #[test]
+    fn route_random_seeds_are_reproducible() {
+        let body = |resp: &str| resp.split("\r\n\r\n").nth(1).unwrap().to_string();
+        let seed_of = |resp: &str| {
+            resp.lines().find_map(|l| l.strip_prefix("X-Random-Seed: ")).unwrap().to_string()
+        };
+        let state = new_state();
+        let first = route("/random?count=10&min=0&max=1000", state.clone());
+        let replay = route(&format!("/random?count=10&min=0&max=1000&seed={}", seed_of(&first)), state.clone());
+        assert_eq!(body(&first), body(&replay));
+
+        // Con semilla de servidor dos arranques dan las mismas secuencias
+        let config = crate::config::Config { random_seed: Some(3), ..Default::default() };
+        let run = || {
+            let state = crate::state::new_state_with(builtin_registry(), config.clone());
+            (0..3).map(|_| route("/random?count=5&min=0&max=99", state.clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
     #[test]
     fn route_createfile_and_deletefile_success() {
         let state = new_state();
@@ -259,6 +482,9 @@ mod tests {
         assert!(resp_ok.contains(
             "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
         ));
+        // `+` y `%XX` se hashean sin decodificar, como siempre
+        assert!(route("/hash?text=a+b%21", state.clone())
+            .contains("b5a273196e5e4c749f08e853edc0e2dd72eabf4491d06083f653412f38cce162"));
     }
 
     #[test]
@@ -272,7 +498,8 @@ mod tests {
         assert!(resp_sl.contains("Espera de 0 segundo(s) completada"));
         // Loadtest
         let resp_lt = route("/loadtest?tasks=1&sleep=0", state.clone());
-        assert!(resp_lt.contains("Carga de 1 tarea(s) con 0 seg de sleep completada en 0 segundo(s)"));
+        assert!(resp_lt.starts_with("HTTP/1.0 200 OK"));
+        assert!(resp_lt.contains("\"tasks\":1"));
     }
 
     #[test]
@@ -289,6 +516,17 @@ mod tests {
         assert!(resp.starts_with("HTTP/1.0 400 Bad Request"));
     }
 
+    #[test]
+    fn route_sleep_and_simulate_subsecond() {
+        let state = new_state();
+        let resp = route("/sleep?duration=5ms", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        assert!(resp.contains("\"requested_ms\":5.0"));
+        let resp = route("/simulate?ms=5&task=t&jitter=1ms", state.clone());
+        assert!(resp.contains("\"task\":\"t\""));
+        assert!(resp.contains("\"jitter_ms\":1.0"));
+    }
+
     #[test]
     fn route_loadtest_missing_or_invalid() {
         let state = new_state();
@@ -297,4 +535,179 @@ mod tests {
         let r2 = route("/loadtest?tasks=2&sleep=abc", state.clone());
         assert!(r2.starts_with("HTTP/1.0 400 Bad Request"));
     }
+
+    #[test]
+    fn route_wrong_method_returns_405() {
+        let state = new_state();
+        let resp = dispatch(Request::new("POST", "/status"), state.clone());
+        assert!(resp.starts_with("HTTP/1.0 405 Method Not Allowed"));
+    }
+
+    #[test]
+    fn help_lists_every_route() {
+        let state = new_state();
+        let resp = route("/help", state.clone());
+        for r in builtin_registry().specs() {
+            assert!(resp.contains(r.path), "falta {} en /help", r.path);
+        }
+    }
+
+    #[test]
+    fn route_help_json_and_openapi() {
+        let state = new_state();
+        let resp = route("/help?format=json", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["routes"].as_array().unwrap().len(), builtin_registry().routes().len());
+
+        let resp = route("/openapi.json", state.clone());
+        let body = resp.split("\r\n\r\n").nth(1).unwrap();
+        let doc: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert!(doc["paths"]["/fibonacci"]["get"].is_object());
+
+        let resp = route("/help?format=xml", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 400 Bad Request"));
+    }
+
+    #[test]
+    fn registered_handler_is_dispatched_and_listed() {
+        let mut reg = builtin_registry();
+        reg.register(
+            get("/ping", &[], "responde pong"),
+            |_: &Request, _: SharedState| "HTTP/1.0 200 OK\r\n\r\npong\n".to_string(),
+        );
+        let state = crate::state::new_state_with(reg, Default::default());
+        assert!(route("/ping", state.clone()).contains("pong"));
+        assert!(route("/help", state.clone()).contains("/ping"));
+    }
+
+    #[test]
+    fn dispatch_counts_every_request_including_404() {
+        let state = new_state();
+        route("/status", state.clone());
+        route("/noexiste", state.clone());
+        assert_eq!(state.lock().unwrap().total_connections, 2);
+    }
+
+    #[test]
+    fn route_middleware_only_wraps_its_route() {
+        let mut reg = builtin_registry();
+        reg.wrap_route("/timestamp", |_: &mut Request, _: &SharedState, _: Next<'_>| {
+            "HTTP/1.0 401 Unauthorized\r\n\r\n".to_string()
+        });
+        let state = crate::state::new_state_with(reg, Default::default());
+        assert!(route("/timestamp", state.clone()).starts_with("HTTP/1.0 401"));
+        assert!(route("/status", state.clone()).starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn route_loadtest_caps_tasks_and_concurrency() {
+        let config = crate::config::Config {
+            loadtest_max_tasks: 8,
+            loadtest_max_concurrency: 2,
+            ..Default::default()
+        };
+        let state = crate::state::new_state_with(builtin_registry(), config);
+        let resp = route("/loadtest?tasks=9&sleep=0", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 400 Bad Request"));
+        assert!(resp.contains("menor o igual que 8"));
+
+        let resp = route("/loadtest?tasks=8&sleep=0&concurrency=5", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 200 OK"));
+        assert!(resp.contains("\"concurrency_requested\":5,\"concurrency\":2"));
+    }
+
+    #[test]
+    fn route_admin_chaos_configures_and_reproduces() {
+        let state = new_state();
+        let resp = route("/admin/chaos?action=set&route=/timestamp&error=1&seed=9", state.clone());
+        assert!(resp.contains("\"seed\":9"), "{}", resp);
+        assert!(route("/timestamp", state.clone()).starts_with("HTTP/1.0 500"));
+        assert!(route("/status", state.clone()).starts_with("HTTP/1.0 200 OK"));
+
+        let resp = route("/admin/chaos", state.clone());
+        assert!(resp.contains("\"injected\":{\"latency\":0,\"error\":1"), "{}", resp);
+
+        assert!(route("/admin/chaos?action=set&error=1.5", state.clone()).starts_with("HTTP/1.0 400"));
+        route("/admin/chaos?action=clear", state.clone());
+        assert!(route("/timestamp", state.clone()).starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn route_math_commands() {
+        let state = new_state();
+        assert!(route("/isprime?n=97", state.clone()).contains("{\"n\":97,\"prime\":true}"));
+        assert!(route("/factor?n=12", state.clone()).contains("\"factors\":[2,2,3]"));
+        assert!(route("/pi?digits=5", state.clone()).ends_with("\r\n\r\n3.1415\n"));
+        assert!(route("/mandelbrot?width=4&height=2", state.clone()).contains("P2\n4 2\n255\n"));
+        assert!(route("/matrixmul?size=3&seed=1", state.clone()).contains("\"seed\":1"));
+        // El timeout pedido acorta el del servidor
+        let resp = route("/pi?digits=20000&timeout=1ms", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 503"), "{}", resp);
+        assert_eq!(builtin_registry().find("/pi").unwrap().spec.pool.workers, 2);
+    }
+
+    #[test]
+    fn cancelled_requests_stop_early_without_response() {
+        let state = new_state();
+        let req = Request::get("/sleep?ms=5000");
+        req.cancel.cancel();
+        let start = std::time::Instant::now();
+        assert_eq!(dispatch(req, state.clone()), "");
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+        let req = Request::get("/pi?digits=20000");
+        req.cancel.cancel();
+        assert!(dispatch(req, state.clone()).starts_with("HTTP/1.0 503"));
+    }
+
+    #[test]
+    fn route_trace_returns_chrome_events() {
+        let state = new_state();
+        let task = state.lock().unwrap().trace.enqueue("/sleep", "/sleep?ms=1");
+        state.lock().unwrap().trace.record(crate::trace::Phase::Start, task, "/sleep", "ThreadId(2)", "/sleep?ms=1");
+        let resp = route("/trace?since=0", state.clone());
+        assert!(resp.contains("\"traceEvents\""), "{}", resp);
+        assert!(resp.contains("\"ph\":\"B\""));
+        assert!(route("/trace?since=-1", state.clone()).starts_with("HTTP/1.0 400"));
+    }
+
+    #[test]
+    fn sliced_sleep_yields_and_resumes() {
+        let state = new_state();
+        let mut req = Request::get("/sleep?ms=30");
+        let mut slices = 0;
+        let resp = loop {
+            slices += 1;
+            if let Some(resp) = dispatch_slice(&mut req, &state, Duration::from_millis(5)) {
+                break resp;
+            }
+            assert!(req.yielded);
+        };
+        assert!(resp.starts_with("HTTP/1.0 200 OK"), "{}", resp);
+        assert!(slices > 1);
+        // Sin límite de tiempo responde en una sola llamada
+        assert!(route("/sleep?ms=1", state.clone()).starts_with("HTTP/1.0 200 OK"));
+        let registry = builtin_registry();
+        assert_eq!(registry.find("/sleep").unwrap().spec.estimate("ms=250"), 250.0);
+    }
+
+    #[test]
+    fn route_text_commands_accept_query_or_body() {
+        let state = new_state();
+        for op in TextOp::ALL {
+            let resp = route(&format!("{}?text=YWJj", op.path()), state.clone());
+            assert!(resp.starts_with("HTTP/1.0 200 OK"), "{}: {}", op.path(), resp);
+            assert!(resp.contains(&format!("\"op\":\"{}\"", op.name())));
+        }
+
+        let mut req = Request::new("POST", "/palindrome");
+        req.body = b"Oso".to_vec();
+        assert!(dispatch(req, state.clone()).contains("\"result\":true"));
+
+        let resp = route("/wordcount", state.clone());
+        assert!(resp.starts_with("HTTP/1.0 400 Bad Request"));
+        assert!(route("/base64decode?text=%40", state.clone()).starts_with("HTTP/1.0 400"));
+    }
 }
@@ -0,0 +1,505 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Disciplina con la que los workers de un pool toman peticiones de su cola
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyKind {
+    /// En orden de llegada
+    Fifo,
+    /// Primero el trabajo de menor costo estimado
+    Sjf,
+    /// Mayor prioridad primero; la espera suma prioridad (aging)
+    Priority,
+    /// En orden de llegada, pero los handlers cooperativos ceden el hilo cada
+    /// `quantum` y vuelven al final de la cola
+    #[serde(rename = "rr")]
+    RoundRobin,
+}
+
+impl PolicyKind {
+    pub const ALL: [PolicyKind; 4] = [PolicyKind::Fifo, PolicyKind::Sjf, PolicyKind::Priority, PolicyKind::RoundRobin];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PolicyKind::Fifo => "fifo",
+            PolicyKind::Sjf => "sjf",
+            PolicyKind::Priority => "priority",
+            PolicyKind::RoundRobin => "rr",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<PolicyKind> {
+        PolicyKind::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+/// Parámetros de las políticas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SchedulerSettings {
+    /// Porción de tiempo de round-robin
+    pub quantum: Duration,
+    /// Puntos de prioridad que gana una petición por segundo de espera
+    pub aging_per_sec: f64,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        SchedulerSettings { quantum: Duration::from_millis(50), aging_per_sec: 1.0 }
+    }
+}
+
+/// Trabajo en cola con los datos que usan las políticas
+pub struct Job<T> {
+    pub payload: T,
+    /// Costo estimado (ms) según los parámetros de la petición
+    pub cost: f64,
+    /// Prioridad pedida (mayor = más urgente)
+    pub priority: i64,
+    /// Llegada al servidor
+    pub arrived: Instant,
+    /// Última vez que entró a la cola (cambia al volver tras ceder el hilo)
+    enqueued: Instant,
+    seq: u64,
+}
+
+trait Policy<T>: Send {
+    fn push(&mut self, job: Job<T>);
+    fn pop(&mut self) -> Option<Job<T>>;
+    fn len(&self) -> usize;
+    fn drain(&mut self) -> Vec<Job<T>>;
+    /// Desde cuándo espera el trabajo que más tiempo lleva en la cola
+    fn oldest(&self) -> Option<Instant>;
+}
+
+/// Cola FIFO; también la usa round-robin
+struct Fifo<T>(VecDeque<Job<T>>);
+
+impl<T: Send> Policy<T> for Fifo<T> {
+    fn push(&mut self, job: Job<T>) {
+        self.0.push_back(job);
+    }
+    fn pop(&mut self) -> Option<Job<T>> {
+        self.0.pop_front()
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn drain(&mut self) -> Vec<Job<T>> {
+        self.0.drain(..).collect()
+    }
+    fn oldest(&self) -> Option<Instant> {
+        self.0.iter().map(|j| j.enqueued).min()
+    }
+}
+
+/// Saca el trabajo con menor `key` (a igualdad, el que llegó antes). Las
+/// colas son cortas, así que alcanza con recorrerlas
+fn pop_min_by<T>(jobs: &mut Vec<Job<T>>, key: impl Fn(&Job<T>) -> f64) -> Option<Job<T>> {
+    let i = (0..jobs.len()).min_by(|&a, &b| {
+        key(&jobs[a]).total_cmp(&key(&jobs[b])).then(jobs[a].seq.cmp(&jobs[b].seq))
+    })?;
+    Some(jobs.swap_remove(i))
+}
+
+struct ShortestJobFirst<T>(Vec<Job<T>>);
+
+impl<T: Send> Policy<T> for ShortestJobFirst<T> {
+    fn push(&mut self, job: Job<T>) {
+        self.0.push(job);
+    }
+    fn pop(&mut self) -> Option<Job<T>> {
+        pop_min_by(&mut self.0, |j| j.cost)
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn drain(&mut self) -> Vec<Job<T>> {
+        std::mem::take(&mut self.0)
+    }
+    fn oldest(&self) -> Option<Instant> {
+        self.0.iter().map(|j| j.enqueued).min()
+    }
+}
+
+struct PriorityAging<T> {
+    jobs: Vec<Job<T>>,
+    aging_per_sec: f64,
+}
+
+impl<T: Send> Policy<T> for PriorityAging<T> {
+    fn push(&mut self, job: Job<T>) {
+        self.jobs.push(job);
+    }
+    fn pop(&mut self) -> Option<Job<T>> {
+        let now = Instant::now();
+        let aging = self.aging_per_sec;
+        // Prioridad efectiva = pedida + espera * aging (negada para sacar el mínimo)
+        pop_min_by(&mut self.jobs, |j| {
+            -(j.priority as f64 + now.duration_since(j.arrived).as_secs_f64() * aging)
+        })
+    }
+    fn len(&self) -> usize {
+        self.jobs.len()
+    }
+    fn drain(&mut self) -> Vec<Job<T>> {
+        std::mem::take(&mut self.jobs)
+    }
+    fn oldest(&self) -> Option<Instant> {
+        self.jobs.iter().map(|j| j.enqueued).min()
+    }
+}
+
+fn make_policy<T: Send + 'static>(kind: PolicyKind, settings: SchedulerSettings) -> Box<dyn Policy<T>> {
+    match kind {
+        PolicyKind::Fifo | PolicyKind::RoundRobin => Box::new(Fifo(VecDeque::new())),
+        PolicyKind::Sjf => Box::new(ShortestJobFirst(Vec::new())),
+        PolicyKind::Priority => Box::new(PriorityAging { jobs: Vec::new(), aging_per_sec: settings.aging_per_sec }),
+    }
+}
+
+/// Contadores de una política (acumulados mientras estuvo activa)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PolicyStats {
+    pub submitted: usize,
+    pub completed: usize,
+    /// Veces que un handler cooperativo cedió el hilo y volvió a la cola
+    pub requeued: usize,
+    /// Sacados de la cola porque el cliente se fue antes de ser atendidos
+    pub abandoned: usize,
+    dispatched: usize,
+    total_wait: Duration,
+    max_wait: Duration,
+    total_turnaround: Duration,
+}
+
+impl PolicyStats {
+    pub fn merge(&mut self, other: &PolicyStats) {
+        self.submitted += other.submitted;
+        self.completed += other.completed;
+        self.requeued += other.requeued;
+        self.abandoned += other.abandoned;
+        self.dispatched += other.dispatched;
+        self.total_wait += other.total_wait;
+        self.max_wait = self.max_wait.max(other.max_wait);
+        self.total_turnaround += other.total_turnaround;
+    }
+
+    pub fn report(&self) -> PolicyReport {
+        let mean_ms = |total: Duration, n: usize| if n == 0 { 0.0 } else { total.as_secs_f64() * 1000.0 / n as f64 };
+        PolicyReport {
+            submitted: self.submitted,
+            completed: self.completed,
+            requeued: self.requeued,
+            abandoned: self.abandoned,
+            mean_wait_ms: mean_ms(self.total_wait, self.dispatched),
+            max_wait_ms: self.max_wait.as_secs_f64() * 1000.0,
+            mean_turnaround_ms: mean_ms(self.total_turnaround, self.completed),
+        }
+    }
+}
+
+/// Resumen serializable de `PolicyStats` para /status
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PolicyReport {
+    pub submitted: usize,
+    pub completed: usize,
+    pub requeued: usize,
+    pub abandoned: usize,
+    /// Espera media en cola por despacho (cada porción cuenta)
+    pub mean_wait_ms: f64,
+    pub max_wait_ms: f64,
+    /// Desde la llegada hasta la respuesta
+    pub mean_turnaround_ms: f64,
+}
+
+struct Inner<T> {
+    kind: PolicyKind,
+    policy: Box<dyn Policy<T>>,
+    seq: u64,
+    stats: BTreeMap<&'static str, PolicyStats>,
+    /// En pausa: se sigue encolando pero no se entrega trabajo
+    held: bool,
+}
+
+impl<T> Inner<T> {
+    fn stats(&mut self) -> &mut PolicyStats {
+        self.stats.entry(self.kind.name()).or_default()
+    }
+}
+
+/// Cola de un pool entre el bucle de aceptación y los workers. La política
+/// decide qué petición toma el siguiente worker libre
+pub struct Scheduler<T> {
+    route: String,
+    settings: SchedulerSettings,
+    inner: Mutex<Inner<T>>,
+    ready: Condvar,
+}
+
+impl<T: Send + 'static> Scheduler<T> {
+    pub fn new(route: &str, kind: PolicyKind, settings: SchedulerSettings) -> Self {
+        Scheduler {
+            route: route.to_string(),
+            settings,
+            inner: Mutex::new(Inner { kind, policy: make_policy(kind, settings), seq: 0, stats: BTreeMap::new(), held: false }),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// Encola una petición nueva
+    pub fn submit(&self, payload: T, cost: f64, priority: i64) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.seq += 1;
+        let job = Job { payload, cost, priority, arrived: now, enqueued: now, seq: inner.seq };
+        inner.policy.push(job);
+        inner.stats().submitted += 1;
+        drop(inner);
+        self.ready.notify_one();
+    }
+
+    /// Devuelve a la cola un trabajo que cedió el hilo (queda detrás de los que esperan)
+    pub fn requeue(&self, mut job: Job<T>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.seq += 1;
+        job.seq = inner.seq;
+        job.enqueued = Instant::now();
+        inner.policy.push(job);
+        inner.stats().requeued += 1;
+        drop(inner);
+        self.ready.notify_one();
+    }
+
+    /// Espera hasta que haya trabajo y lo saca según la política
+    pub fn next(&self) -> Job<T> {
+        loop {
+            if let Some(job) = self.next_timeout(Duration::from_secs(3600)) {
+                return job;
+            }
+        }
+    }
+
+    /// Como `next`, pero se rinde tras `timeout` sin trabajo (así un worker
+    /// ocioso puede revisar si debe retirarse)
+    pub fn next_timeout(&self, timeout: Duration) -> Option<Job<T>> {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some(job) = (!inner.held).then(|| inner.policy.pop()).flatten() {
+                let wait = job.enqueued.elapsed();
+                let stats = inner.stats();
+                stats.dispatched += 1;
+                stats.total_wait += wait;
+                stats.max_wait = stats.max_wait.max(wait);
+                return Some(job);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return None;
+            }
+            inner = self.ready.wait_timeout(inner, left).unwrap().0;
+        }
+    }
+
+    /// Saca de la cola los trabajos que cumplen `gone` (clientes que se
+    /// fueron) y los devuelve; el resto conserva su orden
+    pub fn cancel_where(&self, gone: impl Fn(&T) -> bool) -> Vec<Job<T>> {
+        let mut inner = self.inner.lock().unwrap();
+        let (cancelled, kept): (Vec<_>, Vec<_>) = inner.policy.drain().into_iter().partition(|j| gone(&j.payload));
+        for job in kept {
+            inner.policy.push(job);
+        }
+        inner.stats().abandoned += cancelled.len();
+        cancelled
+    }
+
+    /// Retiene (o libera) la cola: mientras está retenida `next` no entrega nada
+    pub fn hold(&self, held: bool) {
+        self.inner.lock().unwrap().held = held;
+        if !held {
+            self.ready.notify_all();
+        }
+    }
+
+    /// Cuánto lleva esperando el trabajo más antiguo de la cola
+    pub fn oldest_wait(&self) -> Option<Duration> {
+        self.inner.lock().unwrap().policy.oldest().map(|t| t.elapsed())
+    }
+
+    /// Marca un trabajo como terminado (para el tiempo de respuesta)
+    pub fn complete(&self, job: &Job<T>) {
+        let mut inner = self.inner.lock().unwrap();
+        let stats = inner.stats();
+        stats.completed += 1;
+        stats.total_turnaround += job.arrived.elapsed();
+    }
+
+    /// Porción de tiempo para los handlers cooperativos (`None` = sin límite)
+    pub fn quantum(&self) -> Option<Duration> {
+        (self.policy() == PolicyKind::RoundRobin).then_some(self.settings.quantum)
+    }
+}
+
+/// Vista de un scheduler sin importar qué lleva en la cola, para /status
+pub trait SchedulerInfo: Send + Sync {
+    fn route(&self) -> &str;
+    fn policy(&self) -> PolicyKind;
+    /// Cambia de política; lo que estaba en cola pasa a la nueva
+    fn set_policy(&self, kind: PolicyKind);
+    fn queued(&self) -> usize;
+    fn stats(&self) -> BTreeMap<&'static str, PolicyStats>;
+}
+
+impl<T: Send + 'static> SchedulerInfo for Scheduler<T> {
+    fn route(&self) -> &str {
+        &self.route
+    }
+
+    fn policy(&self) -> PolicyKind {
+        self.inner.lock().unwrap().kind
+    }
+
+    fn set_policy(&self, kind: PolicyKind) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut policy = make_policy(kind, self.settings);
+        // SJF y prioridad sacan con `swap_remove`: su cola ya no está en
+        // orden de llegada y FIFO/RR lo necesitan
+        let mut jobs = inner.policy.drain();
+        jobs.sort_by_key(|job| job.seq);
+        for job in jobs {
+            policy.push(job);
+        }
+        inner.policy = policy;
+        inner.kind = kind;
+    }
+
+    fn queued(&self) -> usize {
+        self.inner.lock().unwrap().policy.len()
+    }
+
+    fn stats(&self) -> BTreeMap<&'static str, PolicyStats> {
+        self.inner.lock().unwrap().stats.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain_order(s: &Scheduler<u32>, n: usize) -> Vec<u32> {
+        (0..n).map(|_| s.next().payload).collect()
+    }
+
+    #[test]
+    fn switching_from_sjf_to_fifo_restores_arrival_order() {
+        let s = Scheduler::new("/x", PolicyKind::Sjf, SchedulerSettings::default());
+        for (i, cost) in [9.0, 1.0, 8.0, 7.0, 6.0].into_iter().enumerate() {
+            s.submit(i as u32, cost, 0);
+        }
+        // Tomar la más corta desordena el resto de la cola
+        assert_eq!(drain_order(&s, 1), [1]);
+        s.set_policy(PolicyKind::Fifo);
+        assert_eq!(drain_order(&s, 4), [0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn fifo_keeps_arrival_order() {
+        let s = Scheduler::new("/x", PolicyKind::Fifo, SchedulerSettings::default());
+        for (i, cost) in [5.0, 1.0, 3.0].into_iter().enumerate() {
+            s.submit(i as u32, cost, 0);
+        }
+        assert_eq!(drain_order(&s, 3), [0, 1, 2]);
+        assert_eq!(s.quantum(), None);
+    }
+
+    #[test]
+    fn sjf_takes_cheapest_first() {
+        let s = Scheduler::new("/x", PolicyKind::Sjf, SchedulerSettings::default());
+        for (i, cost) in [5.0, 1.0, 3.0, 1.0].into_iter().enumerate() {
+            s.submit(i as u32, cost, 0);
+        }
+        assert_eq!(drain_order(&s, 4), [1, 3, 2, 0]);
+    }
+
+    #[test]
+    fn priority_ages_waiting_jobs() {
+        let settings = SchedulerSettings { aging_per_sec: 1000.0, ..Default::default() };
+        let s = Scheduler::new("/x", PolicyKind::Priority, settings);
+        s.submit(0, 1.0, 0);
+        s.submit(1, 1.0, 5);
+        assert_eq!(drain_order(&s, 1), [1]);
+
+        // Tras 20 ms de espera el trabajo de prioridad 0 ya vale 20 puntos
+        s.submit(2, 1.0, 0);
+        std::thread::sleep(Duration::from_millis(20));
+        s.submit(3, 1.0, 10);
+        assert_eq!(drain_order(&s, 3), [0, 2, 3]);
+    }
+
+    #[test]
+    fn round_robin_requeues_behind_waiting_jobs() {
+        let s = Scheduler::new("/x", PolicyKind::RoundRobin, SchedulerSettings::default());
+        assert_eq!(s.quantum(), Some(Duration::from_millis(50)));
+        s.submit(0, 1.0, 0);
+        s.submit(1, 1.0, 0);
+        let first = s.next();
+        s.requeue(first);
+        assert_eq!(drain_order(&s, 2), [1, 0]);
+
+        let stats = s.stats()["rr"];
+        assert_eq!((stats.submitted, stats.requeued), (2, 1));
+    }
+
+    #[test]
+    fn next_timeout_gives_up_and_reports_oldest_wait() {
+        let s: Scheduler<u32> = Scheduler::new("/x", PolicyKind::Sjf, SchedulerSettings::default());
+        assert!(s.next_timeout(Duration::from_millis(5)).is_none());
+        assert_eq!(s.oldest_wait(), None);
+        s.submit(0, 1.0, 0);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(s.oldest_wait().unwrap() >= Duration::from_millis(5));
+        assert_eq!(s.next_timeout(Duration::from_millis(5)).unwrap().payload, 0);
+    }
+
+    #[test]
+    fn cancel_where_drops_matching_jobs_keeping_order() {
+        let s = Scheduler::new("/x", PolicyKind::Fifo, SchedulerSettings::default());
+        for i in 0..5u32 {
+            s.submit(i, 1.0, 0);
+        }
+        let gone: Vec<u32> = s.cancel_where(|p| p % 2 == 1).into_iter().map(|j| j.payload).collect();
+        assert_eq!(gone, [1, 3]);
+        assert_eq!(drain_order(&s, 3), [0, 2, 4]);
+        assert_eq!(s.stats()["fifo"].report().abandoned, 2);
+    }
+
+    #[test]
+    fn held_queue_keeps_jobs_until_released() {
+        let s = Scheduler::new("/x", PolicyKind::Fifo, SchedulerSettings::default());
+        s.hold(true);
+        s.submit(0u32, 1.0, 0);
+        assert!(s.next_timeout(Duration::from_millis(5)).is_none());
+        assert_eq!(s.queued(), 1);
+        s.hold(false);
+        assert_eq!(s.next_timeout(Duration::from_millis(5)).unwrap().payload, 0);
+    }
+
+    #[test]
+    fn switching_policy_keeps_queue_and_splits_stats() {
+        let s = Scheduler::new("/x", PolicyKind::Fifo, SchedulerSettings::default());
+        s.submit(0, 9.0, 0);
+        s.submit(1, 1.0, 0);
+        s.set_policy(PolicyKind::Sjf);
+        assert_eq!(s.queued(), 2);
+        let job = s.next();
+        assert_eq!(job.payload, 1);
+        s.complete(&job);
+
+        let stats = s.stats();
+        assert_eq!(stats["fifo"].report().submitted, 2);
+        assert_eq!(stats["sjf"].report().completed, 1);
+    }
+}
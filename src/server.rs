@@ -0,0 +1,358 @@
+
+use std::net::{Shutdown, TcpListener, TcpStream};
+use crate::config::Config;
+use crate::frontend::{parse_buffered, ConnSettings, ConnSlot, Frontend, Progress, Violation, REQUEST_TIMEOUT};
+use crate::http::{Request, ResponseSink};
+use crate::registry::{PoolSpec, Registry};
+use crate::pool::{choose_pool, spawn_autoscaler, spawn_disconnect_watcher, Pool, Task, SHARED_POOL};
+use crate::state::{new_state_with, SharedState};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::router::dispatch_slice;
+use std::fs;
+use std::process;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::collections::HashMap;
+
+/// Arranca el servidor en `config.addr` con las rutas del registro: crea un
+/// pool de workers por ruta y atiende conexiones con el frontend elegido
+/// hasta que falle el listener
+pub fn run(config: Config, registry: Registry) -> io::Result<()> {
+    let listener = TcpListener::bind(&config.addr)?;
+    println!("Servidor iniciado en http://{}", config.addr);
+
+    let trace_file = config.trace_file.clone();
+    let frontend = config.frontend;
+    let state = new_state_with(registry, config);
+    let registry = state.lock().unwrap().registry.clone();
+
+    if let Some(path) = trace_file.clone() {
+        let state = state.clone();
+        let installed = ctrlc::set_handler(move || {
+            dump_trace(&state, &path);
+            process::exit(0);
+        });
+        if let Err(e) = installed {
+            eprintln!("No se pudo instalar el manejador de Ctrl-C: {}", e);
+        }
+    }
+
+    // Un pool por ruta, con el tamaño inicial definido en el registro; los
+    // workers toman las peticiones de la cola según la política configurada
+    // y el auto-escalador ajusta cuántos hay
+    let (policy, settings, scaling, shared_workers) = {
+        let st = state.lock().unwrap();
+        (st.config.scheduler, st.config.scheduler_settings, st.config.scaling, st.config.shared_workers)
+    };
+    let mut pools: HashMap<&'static str, Arc<Pool>> = HashMap::new();
+
+    let start_pool = |name: &str, spec: PoolSpec| {
+        let pool = Pool::new(name, spec, policy, settings);
+        for _ in 0..spec.workers.max(1) {
+            pool.spawn_worker(&state);
+        }
+        state.lock().unwrap().pools.push(pool.clone());
+        pool
+    };
+    // Solo las rutas con pool propio lo tienen; el resto (y las rutas que
+    // no existen, para responder 404) van al pool compartido
+    for spec in registry.specs().filter(|s| s.pool.dedicated) {
+        pools.insert(spec.path, start_pool(spec.path, spec.pool));
+    }
+    let shared = start_pool(SHARED_POOL, PoolSpec { dedicated: false, ..PoolSpec::sized(shared_workers) });
+    let mut all: Vec<Arc<Pool>> = pools.values().cloned().collect();
+    all.push(shared.clone());
+    spawn_autoscaler(all.clone(), state.clone(), scaling);
+    spawn_disconnect_watcher(all, state.clone());
+
+    let limits = state.lock().unwrap().config.connections;
+    let dispatcher = Dispatcher { state: state.clone(), registry, pools, shared, limits };
+    match frontend {
+        Frontend::Events if cfg!(target_os = "linux") => {
+            crate::frontend::serve(&listener, &state, |stream, request, slot| dispatcher.submit(stream, request, slot))?
+        }
+        _ => serve_blocking(&listener, &dispatcher),
+    }
+
+    if let Some(path) = trace_file {
+        dump_trace(&state, &path);
+    }
+    Ok(())
+}
+
+/// Reparte las peticiones completas entre los pools
+struct Dispatcher {
+    state: SharedState,
+    registry: Arc<Registry>,
+    pools: HashMap<&'static str, Arc<Pool>>,
+    shared: Arc<Pool>,
+    limits: ConnSettings,
+}
+
+impl Dispatcher {
+    /// Encola la petición en el pool de su ruta (o en el compartido) con
+    /// su costo estimado y prioridad; el worker que la tome responde por
+    /// `stream`, con `limits.write_timeout` como plazo de cada escritura
+    fn submit(&self, mut stream: TcpStream, mut request: Request, conn: ConnSlot) {
+        let _ = stream.set_write_timeout(Some(self.limits.write_timeout));
+        request.peer = stream.peer_addr().ok();
+        if let Some(peer) = request.peer {
+            println!("Nuevo cliente conectado: {:?}", peer);
+        }
+        request.sink = stream.try_clone().ok().map(ResponseSink::new);
+
+        // Despachar al pool de la ruta o al compartido
+        let route = self.registry.find(&request.path);
+        let dedicated = route.and_then(|r| self.pools.get(r.spec.path));
+        let pool = choose_pool(dedicated, &self.shared);
+        if !pool.admits() {
+            let resp = format!("HTTP/1.0 503 Service Unavailable\r\n\r\nEl pool de '{}' está en pausa\n", request.path);
+            let _ = stream.write_all(resp.as_bytes());
+            return;
+        }
+        let cost = route.map_or(1.0, |r| r.spec.estimate(&request.query));
+        let priority = request.header("X-Priority").and_then(|p| p.trim().parse().ok()).unwrap_or(0);
+        match stream.try_clone() {
+            Ok(stream_clone) => {
+                let trace_id = self.state.lock().unwrap().trace.enqueue(&pool.name, &request.path_and_query());
+                let task = Task {
+                    request,
+                    stream: stream_clone,
+                    trace_id,
+                    conn,
+                };
+                pool.scheduler.submit(task, cost, priority);
+                // El préstamo cuenta recién cuando la petición quedó encolada
+                if let Some(own) = dedicated.filter(|own| !Arc::ptr_eq(own, pool)) {
+                    own.lend_to(pool);
+                }
+            }
+            Err(e) => {
+                error500(stream, &format!("No se pudo clonar el socket: {}", e));
+            }
+        }
+    }
+}
+
+/// El frontend original: lee cada conexión en el hilo de aceptación con
+/// `read` bloqueante antes de aceptar la siguiente. Los plazos de cabeceras
+/// y cuerpo acotan cuánto puede frenarlo un cliente lento (ver `read_request`)
+fn serve_blocking(listener: &TcpListener, dispatcher: &Dispatcher) {
+    let state = &dispatcher.state;
+    let tracker = state.lock().unwrap().connections.clone();
+    let record = |v: Violation| state.lock().unwrap().connection_violations.record(v);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(mut stream) => {
+                let slot = match stream.peer_addr().map(|peer| tracker.open(peer.ip())) {
+                    Ok(Ok(slot)) => slot,
+                    Ok(Err(v)) => { record(v); continue; }
+                    Err(_) => continue,
+                };
+                let request = match read_request(&mut stream, &dispatcher.limits) {
+                    Ok(request) => request,
+                    Err((resp, violation)) => {
+                        if let Some(v) = violation {
+                            record(v);
+                        }
+                        let _ = stream.write_all(resp.as_bytes());
+                        continue;
+                    }
+                };
+                let _ = stream.set_read_timeout(None);
+                dispatcher.submit(stream, request, slot);
+            }
+            Err(e) => {
+                eprintln!("Error en la conexión: {}", e);
+            }
+        }
+    }
+}
+
+/// Escribe la traza de los workers en `path` (formato Chrome Trace Event)
+fn dump_trace(state: &SharedState, path: &str) {
+    let doc = state.lock().unwrap_or_else(|e| e.into_inner()).trace.chrome_json(0);
+    match fs::write(path, doc.to_string()) {
+        Ok(()) => println!("Traza guardada en {}", path),
+        Err(e) => eprintln!("No se pudo guardar la traza en {}: {}", path, e),
+    }
+}
+
+/// Lee una petición entera con `read` bloqueante. Las cabeceras tienen que
+/// llegar (en los paquetes que sea) antes de `header_timeout` desde la
+/// conexión, y el cuerpo antes de `body_timeout` desde que se completaron
+/// las cabeceras, igual que en el frontend por eventos
+///   - Err con la respuesta 400/413/431 si la petición es inválida, excesiva
+///     o el cliente cierra antes de mandarla entera
+///   - Err con 408 y la violación si se vence un plazo
+fn read_request(stream: &mut TcpStream, limits: &ConnSettings) -> Result<Request, (String, Option<Violation>)> {
+    let mut deadline = (Instant::now() + limits.header_timeout, Violation::HeaderTimeout);
+    let mut in_body = false;
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let timed_out = || (REQUEST_TIMEOUT.to_string(), Some(deadline.1));
+        let left = deadline.0.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(timed_out());
+        }
+        let _ = stream.set_read_timeout(Some(left));
+        let eof = match stream.read(&mut chunk) {
+            Ok(0) => true,
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                false
+            }
+            Err(e) if is_timeout(&e) => return Err(timed_out()),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => true,
+        };
+        match parse_buffered(&buf, eof) {
+            Progress::Partial => {}
+            Progress::Body if !in_body => {
+                in_body = true;
+                deadline = (Instant::now() + limits.body_timeout, Violation::BodyTimeout);
+            }
+            Progress::Body => {}
+            Progress::Complete(request) => return Ok(*request),
+            Progress::Invalid(resp) => return Err((resp, None)),
+        }
+    }
+}
+
+/// Despacha la petición y escribe la respuesta; un handler cooperativo
+/// corre a lo sumo `slice`. Un panic en el handler (o inyectado por el modo
+/// caos) no debe matar al hilo: se responde 500 y se sigue. Una respuesta
+/// vacía significa que no queda nada por escribir (el handler ya la envió
+/// por partes o hay que cerrar sin responder)
+///   - false si cedió el hilo antes de terminar (no se escribió nada)
+pub(crate) fn respond_slice(stream: &mut TcpStream, request: &mut Request, state: &SharedState, slice: Duration) -> bool {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| dispatch_slice(request, state, slice)));
+    if request.cancel.is_cancelled() {
+        // El cliente ya no está: no hay a quién responder
+        let _ = stream.shutdown(Shutdown::Both);
+        return true;
+    }
+    match result {
+        Ok(None) => return false,
+        Ok(Some(response)) if response.is_empty() => {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        Ok(Some(response)) => {
+            if let Err(e) = stream.write_all(response.as_bytes())
+                && is_timeout(&e)
+            {
+                // El cliente no lee: se corta para liberar al worker
+                state.lock().unwrap().connection_violations.record(Violation::WriteTimeout);
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+        Err(_) => error500(stream, "El servidor falló atendiendo la petición"),
+    }
+    true
+}
+
+/// Un plazo de lectura o escritura del socket vencido se ve como
+/// `WouldBlock` en Unix y `TimedOut` en Windows
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+fn error500(mut stream: impl Write, msg: &str) {
+    let resp = format!(
+        "HTTP/1.0 500 Internal Server Error\r\n\r\n{}",
+        msg
+    );
+    let _ = stream.write_all(resp.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::MAX_HEAD;
+    use std::thread;
+
+    /// Conecta un cliente que corre `client` y lee la petición del lado del servidor
+    fn read_from(limits: ConnSettings, client: impl FnOnce(TcpStream) + Send + 'static) -> Result<Request, (String, Option<Violation>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let writer = thread::spawn(move || client(stream));
+        let (mut server, _) = listener.accept().unwrap();
+        let result = read_request(&mut server, &limits);
+        drop(server);
+        let _ = writer.join();
+        result
+    }
+
+    #[test]
+    fn headers_in_several_segments_over_1024_bytes() {
+        let request = read_from(ConnSettings::default(), |mut s| {
+            s.write_all(b"POST /reverse HTTP/1.0\r\nContent-Length: 4\r\n").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            s.write_all(format!("X-Relleno: {}\r\n", "a".repeat(2000)).as_bytes()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            s.write_all(b"\r\nhola").unwrap();
+            thread::sleep(Duration::from_millis(50));
+        })
+        .unwrap();
+        assert_eq!(request.path, "/reverse");
+        assert_eq!(request.header("X-Relleno").unwrap().len(), 2000);
+        assert_eq!(request.body, b"hola");
+    }
+
+    #[test]
+    fn header_timeout_covers_the_whole_head() {
+        // Cada byte llega antes del plazo, pero las cabeceras nunca terminan
+        let limits = ConnSettings { header_timeout: Duration::from_millis(200), ..ConnSettings::default() };
+        let (resp, violation) = read_from(limits, |mut s| {
+            let _ = s.write_all(b"GET / HTTP/1.0\r\n");
+            for _ in 0..20 {
+                if s.write_all(b"X").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        })
+        .unwrap_err();
+        assert!(resp.starts_with("HTTP/1.0 408"));
+        assert_eq!(violation, Some(Violation::HeaderTimeout));
+    }
+
+    #[test]
+    fn oversized_head_gets_431() {
+        let (resp, violation) = read_from(ConnSettings::default(), |mut s| {
+            let _ = s.write_all(format!("GET / HTTP/1.0\r\nX: {}", "a".repeat(MAX_HEAD)).as_bytes());
+            thread::sleep(Duration::from_millis(50));
+        })
+        .unwrap_err();
+        assert!(resp.starts_with("HTTP/1.0 431"), "{}", resp);
+        assert_eq!(violation, None);
+    }
+
+    #[test]
+    fn half_closed_client_still_gets_the_response() {
+        // Un puerto libre para el servidor completo (pools y vigilante de desconexiones)
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let config = Config { addr: addr.to_string(), rate_limit: false, ..Config::default() };
+        thread::spawn(move || run(config, crate::router::builtin_registry()));
+        let start = Instant::now();
+        let mut client = loop {
+            match TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(_) if start.elapsed() < Duration::from_secs(2) => thread::sleep(Duration::from_millis(10)),
+                Err(e) => panic!("el servidor no arrancó: {}", e),
+            }
+        };
+
+        // Como `nc -N`: la petición entera y después fin de escritura
+        client.write_all(b"GET /sleep?ms=300 HTTP/1.0\r\n\r\n").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reply = String::new();
+        let _ = client.read_to_string(&mut reply);
+        assert!(reply.starts_with("HTTP/1.0 200 OK"), "{:?}", reply);
+    }
+}
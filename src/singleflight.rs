@@ -0,0 +1,216 @@
+use crate::http::{self, Request};
+use crate::middleware::{Middleware, Next};
+use crate::state::SharedState;
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Cada cuánto revisa un seguidor si su cliente se fue mientras espera
+const CANCEL_CHECK: Duration = Duration::from_millis(10);
+
+/// Cálculo en curso que comparten las peticiones idénticas
+#[derive(Default)]
+struct Flight {
+    /// None mientras corre; Some(None) si terminó sin una respuesta que
+    /// compartir (panic, cliente ido o respuesta enviada por partes)
+    result: Mutex<Option<Option<String>>>,
+    done: Condvar,
+}
+
+impl Flight {
+    fn publish(&self, result: Option<String>) {
+        *self.result.lock().unwrap() = Some(result);
+        self.done.notify_all();
+    }
+}
+
+/// Middleware de ruta que junta las peticiones idénticas en curso: la
+/// primera (el líder) corre el handler y las que llegan mientras tanto
+/// esperan su respuesta en vez de calcularla otra vez. Solo sirve para
+/// rutas deterministas, cuya respuesta depende únicamente de la petición.
+/// Las peticiones que se sumaron se cuentan en `ServerState.coalesced` y
+/// reciben la cabecera `X-Coalesced: true`. Si el líder no deja una
+/// respuesta, cada seguidor la calcula por su cuenta. Con
+/// `--coalesce=off` deja pasar todo
+#[derive(Default)]
+pub struct Singleflight {
+    flights: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+/// Quita el vuelo del mapa al terminar el líder, aunque el handler haga
+/// panic; si no llegó a publicar, los seguidores calculan por su cuenta
+struct Leader<'a> {
+    owner: &'a Singleflight,
+    key: String,
+    flight: Arc<Flight>,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.owner.flights.lock().unwrap().remove(&self.key);
+        let mut result = self.flight.result.lock().unwrap();
+        if result.is_none() {
+            *result = Some(None);
+            self.flight.done.notify_all();
+        }
+    }
+}
+
+impl Singleflight {
+    /// Peticiones iguales comparten clave: mismo método, ruta, parámetros
+    /// (sin importar el orden) y cuerpo
+    pub fn key(req: &Request) -> String {
+        let mut params: Vec<&str> = req.query.split('&').filter(|p| !p.is_empty()).collect();
+        params.sort_unstable();
+        format!("{} {}?{}\n{}", req.method, req.path, params.join("&"), String::from_utf8_lossy(&req.body))
+    }
+
+    /// Espera la respuesta del líder
+    ///   - None si el líder no dejó respuesta o el cliente se fue
+    fn wait(flight: &Flight, req: &Request) -> Option<String> {
+        let mut result = flight.result.lock().unwrap();
+        loop {
+            if let Some(shared) = result.as_ref() {
+                return shared.clone();
+            }
+            if req.cancel.is_cancelled() {
+                return None;
+            }
+            result = flight.done.wait_timeout(result, CANCEL_CHECK).unwrap().0;
+        }
+    }
+}
+
+impl Middleware for Singleflight {
+    fn handle(&self, req: &mut Request, state: &SharedState, next: Next<'_>) -> String {
+        if !state.lock().unwrap().config.coalesce {
+            return next.run(req, state);
+        }
+        let key = Singleflight::key(req);
+        let (flight, leading) = match self.flights.lock().unwrap().entry(key.clone()) {
+            Entry::Occupied(e) => (e.get().clone(), false),
+            Entry::Vacant(e) => (e.insert(Arc::default()).clone(), true),
+        };
+
+        if leading {
+            let leader = Leader { owner: self, key, flight };
+            let resp = next.run(req, state);
+            // Una respuesta vacía no es compartible (ver `respond_slice`)
+            let shared = (!resp.is_empty() && !req.cancel.is_cancelled()).then(|| resp.clone());
+            leader.flight.publish(shared);
+            return resp;
+        }
+        match Singleflight::wait(&flight, req) {
+            Some(resp) => {
+                *state.lock().unwrap().coalesced.entry(req.path.clone()).or_default() += 1;
+                http::with_header(resp, "X-Coalesced", "true")
+            }
+            None if req.cancel.is_cancelled() => String::new(),
+            None => next.run(req, state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::registry::{PoolSpec, Registry, RouteSpec};
+    use crate::router::dispatch;
+    use crate::state::new_state_with;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    fn slow_route(calls: Arc<AtomicUsize>, config: Config) -> SharedState {
+        let spec = RouteSpec {
+            path: "/lento",
+            methods: &["GET"],
+            params: &[],
+            description: "",
+            pool: PoolSpec::DEFAULT,
+            cost: crate::registry::unit_cost,
+            strict_query: false,
+        };
+        let mut reg = Registry::new();
+        reg.register(spec, move |_: &Request, _: SharedState| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(150));
+            format!("HTTP/1.0 200 OK\r\n\r\n{}", n)
+        })
+        .wrap_route("/lento", Singleflight::default());
+        new_state_with(reg, config)
+    }
+
+    fn concurrent(state: &SharedState, paths: &[&str]) -> Vec<String> {
+        let handles: Vec<_> = paths
+            .iter()
+            .map(|p| {
+                let (state, req) = (state.clone(), Request::get(p));
+                thread::spawn(move || dispatch(req, state))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    }
+
+    #[test]
+    fn key_ignores_param_order() {
+        assert_eq!(Singleflight::key(&Request::get("/x?a=1&b=2")), Singleflight::key(&Request::get("/x?b=2&a=1")));
+        assert_ne!(Singleflight::key(&Request::get("/x?a=1")), Singleflight::key(&Request::get("/x?a=2")));
+        let mut post = Request::new("POST", "/x");
+        post.body = b"hola".to_vec();
+        assert_ne!(Singleflight::key(&post), Singleflight::key(&Request::new("POST", "/x")));
+    }
+
+    #[test]
+    fn identical_requests_share_one_computation() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let state = slow_route(calls.clone(), Config::default());
+        let resps = concurrent(&state, &["/lento"; 8]);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(resps.iter().all(|r| r.ends_with("\r\n\r\n0")));
+        assert_eq!(resps.iter().filter(|r| r.contains("X-Coalesced: true")).count(), 7);
+        assert_eq!(state.lock().unwrap().coalesced["/lento"], 7);
+
+        // Terminado el vuelo, la siguiente vuelve a calcular
+        assert!(dispatch(Request::get("/lento"), state.clone()).ends_with("\r\n\r\n1"));
+    }
+
+    #[test]
+    fn disabled_computes_every_request() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let state = slow_route(calls.clone(), Config { coalesce: false, ..Default::default() });
+        concurrent(&state, &["/lento"; 4]);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert!(state.lock().unwrap().coalesced.is_empty());
+    }
+
+    #[test]
+    fn followers_compute_when_leader_leaves_nothing() {
+        let sf = Singleflight::default();
+        let state = crate::state::new_state();
+        let calls = AtomicUsize::new(0);
+        let (entered, release) = (std::sync::Barrier::new(2), std::sync::Barrier::new(2));
+        let leader_endpoint = |_: &mut Request, _: &SharedState| {
+            entered.wait();
+            release.wait();
+            // El líder envió la respuesta por su cuenta (o su cliente se fue)
+            String::new()
+        };
+        let follower_endpoint = |_: &mut Request, _: &SharedState| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            "HTTP/1.0 200 OK\r\n\r\npropia".to_string()
+        };
+        thread::scope(|s| {
+            let leader = s.spawn(|| sf.handle(&mut Request::get("/x"), &state, Next::new(&[], &leader_endpoint)));
+            entered.wait();
+            let follower = s.spawn(|| sf.handle(&mut Request::get("/x"), &state, Next::new(&[], &follower_endpoint)));
+            thread::sleep(Duration::from_millis(30));
+            release.wait();
+            assert_eq!(leader.join().unwrap(), "");
+            assert!(follower.join().unwrap().ends_with("propia"));
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(state.lock().unwrap().coalesced.is_empty());
+    }
+}
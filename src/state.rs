@@ -1,29 +1,110 @@
+use crate::cache::ResponseCache;
+use crate::chaos::Chaos;
+use crate::config::Config;
+use crate::executor::Executor;
+use crate::frontend::{ConnTracker, ConnViolations};
+use crate::registry::Registry;
+use crate::pool::{Pool, ScalingEvent};
+use crate::trace::Tracer;
 use chrono::{DateTime, Utc};
-use std::sync::{Arc, Mutex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// Información de cada worker thread
 pub struct WorkerInfo {
     pub command: String,
     pub thread_id: String,
     pub busy: bool,
+    /// Tiempo de CPU acumulado por el hilo atendiendo peticiones (ms)
+    pub cpu_time_ms: f64,
 }
 
 /// Estado compartido del servidor
 pub struct ServerState {
     pub start_time: DateTime<Utc>,
     pub total_connections: usize,
+    /// Peticiones rechazadas con 429 por el rate limiter
+    pub rate_limited: usize,
+    /// Peticiones que recibieron la respuesta de un cálculo idéntico en curso, por ruta
+    pub coalesced: BTreeMap<String, usize>,
+    /// Peticiones descartadas porque el cliente se desconectó antes de la respuesta
+    pub client_abandoned: usize,
+    /// Conexiones abiertas cuya petición todavía no llegó entera
+    pub pending_connections: usize,
+    /// Conexiones abiertas (en total y por IP) contra los topes de la configuración
+    pub connections: Arc<ConnTracker>,
+    /// Conexiones cortadas por plazos vencidos o topes superados
+    pub connection_violations: ConnViolations,
     pub pid: u32,
     pub workers: Vec<WorkerInfo>,
+    /// Rutas registradas al arrancar
+    pub registry: Arc<Registry>,
+    pub config: Config,
+    /// Respuestas guardadas de las rutas deterministas (ver `cache::CacheLayer`)
+    pub cache: ResponseCache,
+    /// Reglas, semilla y contadores del modo caos (sin reglas no inyecta nada)
+    pub chaos: Chaos,
+    /// Pools de workers por ruta (los llena `server::run` al crearlos)
+    pub pools: Vec<Arc<Pool>>,
+    /// Últimos cambios de tamaño hechos por el auto-escalador
+    pub scaling_events: VecDeque<ScalingEvent>,
+    /// Línea de tiempo de las tareas de los pools (para /trace)
+    pub trace: Tracer,
+    /// Generador de semillas para /random (sembrado con `config.random_seed`)
+    random_seeds: StdRng,
+    /// Pool acotado donde corren las tareas de /loadtest (se crea al primer uso)
+    loadtest_executor: OnceLock<Arc<Executor>>,
+}
+
+impl ServerState {
+    pub fn loadtest_executor(&self) -> Arc<Executor> {
+        self.loadtest_executor
+            .get_or_init(|| Arc::new(Executor::new("loadtest", self.config.loadtest_max_concurrency)))
+            .clone()
+    }
+
+    /// Semilla para una petición a /random que no trae la suya
+    pub fn next_random_seed(&mut self) -> u64 {
+        self.random_seeds.r#gen()
+    }
 }
 
 pub type SharedState = Arc<Mutex<ServerState>>;
 
+/// Estado con los comandos incluidos en el servidor y la configuración por defecto
 pub fn new_state() -> SharedState {
+    new_state_with(crate::router::builtin_registry(), Config::default())
+}
+
+/// Estado con un registro de rutas propio (comandos adicionales ya registrados)
+pub fn new_state_with(registry: Registry, config: Config) -> SharedState {
+    let chaos = Chaos::new(config.chaos_seed.unwrap_or_else(rand::random));
+    let random_seeds = match config.random_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
     Arc::new(Mutex::new(ServerState {
         start_time: Utc::now(),
         total_connections: 0,
+        rate_limited: 0,
+        coalesced: BTreeMap::new(),
+        client_abandoned: 0,
+        pending_connections: 0,
+        connections: ConnTracker::new(&config.connections),
+        connection_violations: ConnViolations::default(),
         pid: std::process::id(),
         workers: Vec::new(),
+        registry: Arc::new(registry),
+        cache: ResponseCache::new(config.cache.clone()),
+        config,
+        chaos,
+        pools: Vec::new(),
+        scaling_events: VecDeque::new(),
+        trace: Tracer::default(),
+        random_seeds,
+        loadtest_executor: OnceLock::new(),
     }))
 }
 
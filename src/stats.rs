@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+/// Resumen de latencias en milisegundos
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl LatencySummary {
+    /// Calcula el resumen (ordena `samples` en el proceso). Vacío si no hay muestras
+    pub fn from_samples(samples: &mut [f64]) -> Self {
+        if samples.is_empty() {
+            return LatencySummary::default();
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let sum: f64 = samples.iter().sum();
+        LatencySummary {
+            count: samples.len(),
+            min: samples[0],
+            mean: sum / samples.len() as f64,
+            p50: percentile(samples, 50.0),
+            p95: percentile(samples, 95.0),
+            p99: percentile(samples, 99.0),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Percentil por rango más cercano sobre muestras ya ordenadas
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Límites superiores (ms, exclusivos) de los buckets del histograma;
+/// el último bucket recoge todo lo que supera el mayor
+pub const HISTOGRAM_BOUNDS_MS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0];
+
+/// Bucket de un histograma de latencias
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    /// Límite superior exclusivo en ms (`None` = sin límite)
+    pub lt_ms: Option<f64>,
+    pub count: usize,
+}
+
+/// Histograma de latencias con buckets fijos en escala aproximadamente logarítmica
+pub fn histogram(samples: &[f64]) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = HISTOGRAM_BOUNDS_MS
+        .iter()
+        .map(|&b| HistogramBucket { lt_ms: Some(b), count: 0 })
+        .chain(std::iter::once(HistogramBucket { lt_ms: None, count: 0 }))
+        .collect();
+    for &v in samples {
+        let i = HISTOGRAM_BOUNDS_MS.iter().position(|&b| v < b).unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        buckets[i].count += 1;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_of_one_to_hundred() {
+        let mut samples: Vec<f64> = (1..=100).rev().map(|v| v as f64).collect();
+        let s = LatencySummary::from_samples(&mut samples);
+        assert_eq!(s.count, 100);
+        assert_eq!(s.min, 1.0);
+        assert_eq!(s.max, 100.0);
+        assert_eq!(s.mean, 50.5);
+        assert_eq!(s.p50, 50.0);
+        assert_eq!(s.p95, 95.0);
+        assert_eq!(s.p99, 99.0);
+    }
+
+    #[test]
+    fn summary_empty() {
+        assert_eq!(LatencySummary::from_samples(&mut []), LatencySummary::default());
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let h = histogram(&[0.5, 1.0, 1.5, 7000.0]);
+        assert_eq!(h.len(), HISTOGRAM_BOUNDS_MS.len() + 1);
+        assert_eq!(h[0].count, 1);
+        assert_eq!(h[1].count, 2);
+        assert_eq!(h.last().unwrap().count, 1);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::registry::{ParamKind, ParamSpec, Params};
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::time::Instant;
+
+/// Eventos que se guardan como máximo; al llenarse se descartan los más viejos
+pub const TRACE_CAPACITY: usize = 100_000;
+
+/// Momento de la vida de una tarea
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Entró a la cola de su pool
+    Enqueue,
+    /// Un worker la tomó
+    Start,
+    /// El worker terminó de responderla
+    Finish,
+    /// El handler cedió el hilo y la tarea volvió a la cola (round-robin)
+    Yield,
+    /// El cliente se fue: se sacó de la cola o se cortó en el worker
+    Abandon,
+}
+
+/// Evento de la línea de tiempo de los workers
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceEvent {
+    /// Microsegundos desde el arranque del servidor
+    pub ts_us: u64,
+    pub phase: Phase,
+    pub task: u64,
+    pub pool: String,
+    /// Hilo del worker (`None` al encolar, lo hace el bucle de aceptación)
+    pub worker: Option<String>,
+    pub path: String,
+}
+
+/// Registro acotado de eventos de las tareas de los pools
+pub struct Tracer {
+    start: Instant,
+    events: VecDeque<TraceEvent>,
+    capacity: usize,
+    next_task: u64,
+    /// Eventos descartados por falta de espacio
+    pub dropped: usize,
+}
+
+impl Tracer {
+    pub fn new(capacity: usize) -> Self {
+        Tracer { start: Instant::now(), events: VecDeque::new(), capacity, next_task: 0, dropped: 0 }
+    }
+
+    fn push(&mut self, phase: Phase, task: u64, pool: &str, worker: Option<&str>, path: &str) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(TraceEvent {
+            ts_us: self.start.elapsed().as_micros() as u64,
+            phase,
+            task,
+            pool: pool.to_string(),
+            worker: worker.map(str::to_string),
+            path: path.to_string(),
+        });
+    }
+
+    /// Registra la llegada de una tarea y devuelve su identificador
+    pub fn enqueue(&mut self, pool: &str, path: &str) -> u64 {
+        self.next_task += 1;
+        let task = self.next_task;
+        self.push(Phase::Enqueue, task, pool, None, path);
+        task
+    }
+
+    /// Registra una tarea abandonada por el cliente, en la cola (`worker`
+    /// `None`) o mientras la atendía un worker
+    pub fn abandon(&mut self, task: u64, pool: &str, worker: Option<&str>, path: &str) {
+        self.push(Phase::Abandon, task, pool, worker, path);
+    }
+
+    /// Registra un evento de un worker sobre una tarea ya encolada
+    pub fn record(&mut self, phase: Phase, task: u64, pool: &str, worker: &str, path: &str) {
+        self.push(phase, task, pool, Some(worker), path);
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.events.iter()
+    }
+
+    /// Documento Chrome Trace Event (formato objeto) con las tareas que
+    /// tuvieron algún evento desde `since_us`. Se filtra por tarea y no por
+    /// evento para que un `E` posterior no quede sin el `B` de antes. Cada
+    /// pool es un proceso y cada worker un hilo; la fila 0 de cada pool es
+    /// su cola, donde se marcan las llegadas
+    pub fn chrome_json(&self, since_us: u64) -> Value {
+        let mut pools: Vec<&str> = Vec::new();
+        let mut workers: Vec<(&str, &str)> = Vec::new();
+        for e in &self.events {
+            if !pools.contains(&e.pool.as_str()) {
+                pools.push(&e.pool);
+            }
+            if let Some(w) = &e.worker
+                && !workers.contains(&(e.pool.as_str(), w.as_str()))
+            {
+                workers.push((&e.pool, w));
+            }
+        }
+        let pid = |pool: &str| pools.iter().position(|p| *p == pool).unwrap() + 1;
+        let tid = |pool: &str, worker: &str| {
+            workers.iter().filter(|(p, _)| *p == pool).position(|(_, w)| *w == worker).unwrap() + 1
+        };
+
+        let mut out = Vec::new();
+        for pool in &pools {
+            out.push(json!({"ph": "M", "name": "process_name", "pid": pid(pool), "tid": 0, "args": {"name": pool}}));
+            out.push(json!({"ph": "M", "name": "thread_name", "pid": pid(pool), "tid": 0, "args": {"name": "cola"}}));
+        }
+        for (pool, worker) in &workers {
+            out.push(json!({
+                "ph": "M", "name": "thread_name", "pid": pid(pool), "tid": tid(pool, worker), "args": {"name": worker},
+            }));
+        }
+
+        let active: HashSet<u64> = self.events.iter().filter(|e| e.ts_us >= since_us).map(|e| e.task).collect();
+        for e in self.events.iter().filter(|e| active.contains(&e.task)) {
+            let mut event = json!({
+                "name": e.path,
+                "cat": e.pool,
+                "ts": e.ts_us,
+                "pid": pid(&e.pool),
+                "tid": e.worker.as_deref().map_or(0, |w| tid(&e.pool, w)),
+                "args": {"task": e.task},
+            });
+            match e.phase {
+                Phase::Enqueue => {
+                    event["ph"] = json!("i");
+                    event["s"] = json!("t");
+                }
+                Phase::Start => event["ph"] = json!("B"),
+                Phase::Finish => event["ph"] = json!("E"),
+                Phase::Yield => {
+                    event["ph"] = json!("E");
+                    event["args"]["yielded"] = json!(true);
+                }
+                // En un worker cierra la franja; en la cola es una marca
+                Phase::Abandon => {
+                    event["ph"] = json!(if e.worker.is_some() { "E" } else { "i" });
+                    if e.worker.is_none() {
+                        event["s"] = json!("t");
+                    }
+                    event["args"]["abandoned"] = json!(true);
+                }
+            }
+            out.push(event);
+        }
+
+        json!({
+            "traceEvents": out,
+            "displayTimeUnit": "ms",
+            "otherData": {"dropped": self.dropped},
+        })
+    }
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer::new(TRACE_CAPACITY)
+    }
+}
+
+/// Parámetros de /trace
+pub const TRACE_PARAMS: &[ParamSpec] = &[
+    ParamSpec::optional("since", ParamKind::UInt, "0", "solo eventos desde este instante (µs desde el arranque, campo `ts`)"),
+];
+
+/// Handler de /trace: los eventos en formato Chrome Trace Event
+pub fn handle_trace(params: &Params, tracer: &Tracer) -> String {
+    let doc = tracer.chrome_json(params.u64("since").unwrap_or_default());
+    format!(
+        "HTTP/1.0 200 OK\r\n\
+         Content-Type: application/json\r\n\r\n\
+         {}\n",
+        doc
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Tracer {
+        let mut t = Tracer::new(10);
+        let a = t.enqueue("/sleep", "/sleep?ms=5");
+        let b = t.enqueue("/fibonacci", "/fibonacci?num=3");
+        t.record(Phase::Start, a, "/sleep", "ThreadId(3)", "/sleep?ms=5");
+        t.record(Phase::Yield, a, "/sleep", "ThreadId(3)", "/sleep?ms=5");
+        t.record(Phase::Start, b, "/fibonacci", "ThreadId(7)", "/fibonacci?num=3");
+        t.record(Phase::Finish, b, "/fibonacci", "ThreadId(7)", "/fibonacci?num=3");
+        t
+    }
+
+    #[test]
+    fn chrome_json_has_metadata_and_phases() {
+        let doc = sample().chrome_json(0);
+        let events = doc["traceEvents"].as_array().unwrap();
+        let names: Vec<_> = events.iter().filter(|e| e["ph"] == "M").map(|e| e["args"]["name"].clone()).collect();
+        assert!(names.contains(&json!("/sleep")));
+        assert!(names.contains(&json!("ThreadId(7)")));
+
+        let phases: Vec<_> = events.iter().filter(|e| e["ph"] != "M").map(|e| e["ph"].as_str().unwrap()).collect();
+        assert_eq!(phases, ["i", "i", "B", "E", "B", "E"]);
+        let yielded = events.iter().find(|e| e["args"]["yielded"] == true).unwrap();
+        assert_eq!(yielded["pid"], 1);
+        assert_eq!(yielded["tid"], 1);
+        // Las llegadas van en la fila de la cola
+        assert_eq!(events.iter().find(|e| e["ph"] == "i").unwrap()["tid"], 0);
+    }
+
+    #[test]
+    fn since_filters_but_keeps_metadata() {
+        let t = sample();
+        let doc = t.chrome_json(u64::MAX);
+        let events = doc["traceEvents"].as_array().unwrap();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|e| e["ph"] == "M"));
+    }
+
+    #[test]
+    fn since_keeps_whole_tasks() {
+        let mut t = Tracer::new(10);
+        let a = t.enqueue("/x", "/x");
+        let b = t.enqueue("/x", "/x");
+        t.record(Phase::Start, a, "/x", "ThreadId(2)", "/x");
+        t.record(Phase::Finish, a, "/x", "ThreadId(2)", "/x");
+        t.record(Phase::Start, b, "/x", "ThreadId(2)", "/x");
+        t.record(Phase::Finish, b, "/x", "ThreadId(2)", "/x");
+        // a termina antes de `since`; b empieza antes y termina después
+        for (e, ts) in t.events.iter_mut().zip([0, 0, 10, 20, 100, 300]) {
+            e.ts_us = ts;
+        }
+
+        let doc = t.chrome_json(200);
+        let events: Vec<_> = doc["traceEvents"].as_array().unwrap().iter().filter(|e| e["ph"] != "M").collect();
+        assert!(events.iter().all(|e| e["args"]["task"] == b));
+        let phases: Vec<_> = events.iter().map(|e| e["ph"].as_str().unwrap()).collect();
+        assert_eq!(phases, ["i", "B", "E"]);
+    }
+
+    #[test]
+    fn capacity_drops_oldest() {
+        let mut t = Tracer::new(3);
+        for _ in 0..5 {
+            t.enqueue("/x", "/x");
+        }
+        assert_eq!(t.events().count(), 3);
+        assert_eq!(t.dropped, 2);
+        assert_eq!(t.events().next().unwrap().task, 3);
+    }
+}